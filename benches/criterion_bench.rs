@@ -10,15 +10,14 @@ pub fn massive_random(c: &mut Criterion) {
     let json_payload = read_to_string(&path).unwrap();
 
     c.bench_function("load", |b| {
-        b.iter(|| JSONValue::parse_and_verify(&json_payload))
+        b.iter(|| JSONValue::load_and_verify(&json_payload))
     });
 
-    let value = JSONValue::parse(&json_payload);
+    let value = JSONValue::load(&json_payload);
     c.bench_function("single_retrieve", |b| {
         b.iter(|| {
             assert_eq!(
                 value
-                    .unwrap()
                     .iter_array()
                     .unwrap()
                     .nth(5)
@@ -40,10 +39,10 @@ pub fn large_array(c: &mut Criterion) {
     let json_payload = read_to_string(&path).unwrap();
 
     c.bench_function("load_array", |b| {
-        b.iter(|| JSONValue::parse_and_verify(&json_payload))
+        b.iter(|| JSONValue::load_and_verify(&json_payload))
     });
 
-    let json = JSONValue::parse(&json_payload).unwrap();
+    let json = JSONValue::load(&json_payload);
     c.bench_function("read_array_sequentially", |b| {
         b.iter(|| {
             for (i, n) in json.iter_array().unwrap().enumerate() {
@@ -67,7 +66,44 @@ pub fn large_array(c: &mut Criterion) {
             }
         })
     });
+
+    c.bench_function("read_array_out_of_order_indexed", |b| {
+        let mut buffer = vec![0; json.len().unwrap()];
+        b.iter(|| {
+            let index = json.index_into(&mut buffer).unwrap();
+            let mut i = 1;
+            for _ in 0..10007 {
+                assert_eq!(
+                    index.get_index(i as usize).unwrap().read_integer(),
+                    Ok(i * i)
+                );
+                i = (i * 5) % 10007;
+            }
+        })
+    });
+}
+
+/// Floats that miss the fast path of the float parser, which is the slow case for
+/// `compact_number_parsing`
+#[cfg(feature = "float")]
+pub fn long_floats(c: &mut Criterion) {
+    let digits = "1234567890".repeat(100);
+    let payload = format!(
+        "[0.1, 3.14159265358979323846, 2.4703282292062328e-324, 1.7976931348623157e308, 1.{}e-300]",
+        digits
+    );
+    let json = JSONValue::load(&payload);
+    c.bench_function("read_long_floats", |b| {
+        b.iter(|| {
+            for value in json.iter_array().unwrap() {
+                assert!(value.read_number::<f64>().is_ok());
+            }
+        })
+    });
 }
 
+#[cfg(feature = "float")]
+criterion_group!(benches, massive_random, large_array, long_floats);
+#[cfg(not(feature = "float"))]
 criterion_group!(benches, massive_random, large_array);
 criterion_main!(benches);
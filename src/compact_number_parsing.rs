@@ -0,0 +1,520 @@
+//! Correctly rounded float parsing, used instead of `core`'s parser by the
+//! `compact_number_parsing` feature
+//!
+//! Numbers with few enough digits are converted exactly with a single float operation (Clinger's
+//! fast path). Anything else is converted by shifting the decimal digits by powers of two, as in
+//! the "simple decimal conversion" fallback of Go's `strconv` and of `core` itself. This is slower
+//! than `core`'s Eisel-Lemire algorithm, but needs no large tables of powers. Up to 768 significant
+//! digits are kept, which is enough to always round correctly, and any beyond that are only noted
+//! to be non-zero.
+//!
+//! This bounds the cost of the fallback.  It keeps the digits on the stack, in a [`Decimal`] of
+//! under 800 bytes, and makes fewer than 70 shifts, each a pass over the stored digits.  A
+//! number of any length therefore takes time linear in its length to read, plus at most some tens
+//! of thousands of digit operations.  That is still far slower than `core`, by a factor of about a
+//! hundred in the `read_long_floats` benchmark.
+
+use core::ops::{Div, Mul, Neg};
+
+/// The floating point types that can be parsed
+trait Float: 'static + Copy + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self> {
+    /// The number of explicitly stored mantissa bits
+    const MANTISSA_BITS: u32;
+    const EXPONENT_BITS: u32;
+    /// The exponent of the smallest normal numbers, less one
+    const MIN_EXPONENT: i32;
+    /// Mantissas up to this, and the powers of ten in this table, are exactly representable
+    const MAX_EXACT_MANTISSA: u64;
+    const EXACT_POWERS_OF_TEN: &'static [Self];
+
+    fn from_mantissa(mantissa: u64) -> Self;
+    fn from_bits(bits: u64) -> Self;
+}
+
+impl Float for f32 {
+    const MANTISSA_BITS: u32 = 23;
+    const EXPONENT_BITS: u32 = 8;
+    const MIN_EXPONENT: i32 = -127;
+    const MAX_EXACT_MANTISSA: u64 = 1 << 24;
+    const EXACT_POWERS_OF_TEN: &'static [Self] =
+        &[1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10];
+
+    fn from_mantissa(mantissa: u64) -> Self {
+        mantissa as f32
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        f32::from_bits(bits as u32)
+    }
+}
+
+impl Float for f64 {
+    const MANTISSA_BITS: u32 = 52;
+    const EXPONENT_BITS: u32 = 11;
+    const MIN_EXPONENT: i32 = -1023;
+    const MAX_EXACT_MANTISSA: u64 = 1 << 53;
+    const EXACT_POWERS_OF_TEN: &'static [Self] = &[
+        1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+        1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+    ];
+
+    fn from_mantissa(mantissa: u64) -> Self {
+        mantissa as f64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        f64::from_bits(bits)
+    }
+}
+
+/// The number of significant digits kept
+const MAX_DIGITS: usize = 768;
+
+/// The largest shift that can be done at once without overflowing a `u64`
+const MAX_SHIFT: u32 = 60;
+
+/// How far to shift by to move the decimal point by at least a given number of places
+const POWERS_OF_TWO: [u32; 9] = [1, 3, 6, 9, 13, 16, 19, 23, 26];
+
+/// An arbitrary precision decimal number, `0.d₀d₁d₂... × 10^point`
+struct Decimal {
+    /// The significant digits, each from 0 to 9, without leading or trailing zeros
+    digits: [u8; MAX_DIGITS],
+    len: usize,
+    point: i32,
+    /// Whether any of the digits after the stored ones are non-zero
+    truncated: bool,
+}
+
+impl Decimal {
+    fn trim(&mut self) {
+        while self.len > 0 && self.digits[self.len - 1] == 0 {
+            self.len -= 1;
+        }
+        if self.len == 0 {
+            self.point = 0;
+        }
+    }
+
+    /// Write a digit at `index` if there is room for it
+    fn write(&mut self, index: usize, digit: u8) {
+        if index < MAX_DIGITS {
+            self.digits[index] = digit;
+        } else if digit != 0 {
+            self.truncated = true;
+        }
+    }
+
+    /// Multiply by `2^shift`
+    fn left_shift(&mut self, shift: u32) {
+        // Find how many digits are gained by multiplying once without writing anything down
+        let mut carry: u64 = 0;
+        for &digit in self.digits[..self.len].iter().rev() {
+            carry = (carry + ((digit as u64) << shift)) / 10;
+        }
+        let mut gained = 0;
+        while carry > 0 {
+            carry /= 10;
+            gained += 1;
+        }
+
+        let mut write = self.len + gained;
+        let mut n: u64 = 0;
+        for read in (0..self.len).rev() {
+            n += (self.digits[read] as u64) << shift;
+            write -= 1;
+            self.write(write, (n % 10) as u8);
+            n /= 10;
+        }
+        while n > 0 {
+            write -= 1;
+            self.write(write, (n % 10) as u8);
+            n /= 10;
+        }
+        self.len = usize::min(self.len + gained, MAX_DIGITS);
+        self.point += gained as i32;
+        self.trim();
+    }
+
+    /// Divide by `2^shift`, rounding down
+    fn right_shift(&mut self, shift: u32) {
+        let mut read = 0;
+        let mut n: u64 = 0;
+        // Read digits until there is something to divide
+        while n >> shift == 0 {
+            if read < self.len {
+                n = n * 10 + self.digits[read] as u64;
+            } else if n == 0 {
+                self.len = 0;
+                return;
+            } else {
+                n *= 10;
+            }
+            read += 1;
+        }
+        self.point -= read as i32 - 1;
+
+        let mask = (1 << shift) - 1;
+        let mut write = 0;
+        while read < self.len {
+            self.digits[write] = (n >> shift) as u8;
+            write += 1;
+            n = (n & mask) * 10 + self.digits[read] as u64;
+            read += 1;
+        }
+        while n > 0 {
+            if write < MAX_DIGITS {
+                self.digits[write] = (n >> shift) as u8;
+                write += 1;
+            } else if n >> shift > 0 {
+                self.truncated = true;
+            }
+            n = (n & mask) * 10;
+        }
+        self.len = write;
+        self.trim();
+    }
+
+    /// Multiply by `2^shift`, or divide if it is negative
+    fn shift(&mut self, mut shift: i32) {
+        while shift > 0 {
+            let step = u32::min(shift as u32, MAX_SHIFT);
+            self.left_shift(step);
+            shift -= step as i32;
+        }
+        while shift < 0 {
+            let step = u32::min(shift.unsigned_abs(), MAX_SHIFT);
+            self.right_shift(step);
+            shift += step as i32;
+        }
+    }
+
+    /// Round to the nearest integer, with ties going to the even integer
+    fn round(&self) -> u64 {
+        if self.point > 20 {
+            return u64::MAX;
+        } else if self.point < 0 {
+            // Less than a tenth
+            return 0;
+        }
+        let point = self.point as usize;
+        let mut n: u64 = 0;
+        for index in 0..point {
+            n = n * 10 + *self.digits[..self.len].get(index).unwrap_or(&0) as u64;
+        }
+        let round_up = match self.digits[..self.len].get(point) {
+            // Exactly halfway
+            Some(5) if point + 1 == self.len && !self.truncated => n % 2 == 1,
+            Some(&digit) => digit >= 5,
+            None => false,
+        };
+        n + round_up as u64
+    }
+
+    /// Convert to the nearest float, or `None` if it is too large
+    fn nearest_float<F: Float>(&mut self) -> Option<F> {
+        if self.len == 0 || self.point < -330 {
+            return Some(F::from_bits(0));
+        }
+        if self.point > 310 {
+            return None;
+        }
+
+        // Shift until the number is in [1/2, 1), keeping track of the binary exponent
+        let mut exponent: i32 = 0;
+        while self.point > 0 {
+            let shift = *POWERS_OF_TWO.get(self.point as usize).unwrap_or(&27);
+            self.right_shift(shift);
+            exponent += shift as i32;
+        }
+        while self.point < 0 || (self.point == 0 && self.digits[0] < 5) {
+            let shift = *POWERS_OF_TWO
+                .get(self.point.unsigned_abs() as usize)
+                .unwrap_or(&27);
+            self.left_shift(shift);
+            exponent -= shift as i32;
+        }
+        // Now in [1, 2)
+        exponent -= 1;
+
+        // Subnormal numbers have fewer bits of mantissa
+        if exponent < F::MIN_EXPONENT + 1 {
+            let shift = F::MIN_EXPONENT + 1 - exponent;
+            self.shift(-shift);
+            exponent += shift;
+        }
+        let max_exponent = (1 << F::EXPONENT_BITS) - 1;
+        if exponent - F::MIN_EXPONENT >= max_exponent {
+            return None;
+        }
+
+        self.shift(F::MANTISSA_BITS as i32 + 1);
+        let mut mantissa = self.round();
+        // Rounding may carry into another bit
+        if mantissa == 2 << F::MANTISSA_BITS {
+            mantissa >>= 1;
+            exponent += 1;
+            if exponent - F::MIN_EXPONENT >= max_exponent {
+                return None;
+            }
+        }
+        if mantissa & (1 << F::MANTISSA_BITS) == 0 {
+            exponent = F::MIN_EXPONENT;
+        }
+
+        let bits = (mantissa & ((1 << F::MANTISSA_BITS) - 1))
+            | (((exponent - F::MIN_EXPONENT) as u64) << F::MANTISSA_BITS);
+        Some(F::from_bits(bits))
+    }
+}
+
+fn parse<F: Float>(value: &str) -> Result<F, ()> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let mut decimal = Decimal {
+        digits: [0; MAX_DIGITS],
+        len: 0,
+        point: 0,
+        truncated: false,
+    };
+    let mut bytes = value.as_bytes().iter();
+    let mut seen_digit = false;
+    let mut seen_decimal = false;
+    let mut seen_exponent = false;
+    for &b in bytes.by_ref() {
+        match b {
+            b'0'..=b'9' => {
+                seen_digit = true;
+                let digit = b - b'0';
+                if decimal.len == 0 && digit == 0 {
+                    // Leading zeros only move the decimal point
+                    if seen_decimal {
+                        decimal.point -= 1;
+                    }
+                    continue;
+                }
+                decimal.write(decimal.len, digit);
+                decimal.len = usize::min(decimal.len + 1, MAX_DIGITS);
+                if !seen_decimal {
+                    decimal.point = decimal.point.saturating_add(1);
+                }
+            }
+            b'.' if !seen_decimal => seen_decimal = true,
+            b'e' | b'E' => {
+                seen_exponent = true;
+                break;
+            }
+            _ => return Err(()),
+        }
+    }
+    if !seen_digit {
+        return Err(());
+    }
+    if seen_exponent {
+        let exponent = bytes.as_slice();
+        let (exponent_negative, digits) = match exponent.first() {
+            Some(b'-') => (true, &exponent[1..]),
+            Some(b'+') => (false, &exponent[1..]),
+            _ => (false, exponent),
+        };
+        if digits.is_empty() {
+            return Err(());
+        }
+        let mut explicit_exponent: i32 = 0;
+        for &b in digits {
+            if !b.is_ascii_digit() {
+                return Err(());
+            }
+            // Saturate rather than overflow, as the result is zero or infinite either way
+            explicit_exponent = explicit_exponent
+                .saturating_mul(10)
+                .saturating_add((b - b'0') as i32);
+        }
+        if exponent_negative {
+            explicit_exponent = -explicit_exponent;
+        }
+        decimal.point = decimal.point.saturating_add(explicit_exponent);
+    }
+    decimal.trim();
+
+    let value: F = fast_path(&decimal)
+        .or_else(|| decimal.nearest_float())
+        .ok_or(())?;
+    Ok(if negative { -value } else { value })
+}
+
+/// Convert small numbers exactly, using a single correctly rounded float operation
+fn fast_path<F: Float>(decimal: &Decimal) -> Option<F> {
+    if decimal.truncated || decimal.len > 19 {
+        return None;
+    }
+    let mantissa = decimal.digits[..decimal.len]
+        .iter()
+        .fold(0, |n, &digit| n * 10 + digit as u64);
+    if mantissa > F::MAX_EXACT_MANTISSA {
+        return None;
+    }
+    let exponent = decimal.point - decimal.len as i32;
+    let power = F::EXACT_POWERS_OF_TEN.get(exponent.unsigned_abs() as usize)?;
+    let mantissa = F::from_mantissa(mantissa);
+    Some(if exponent < 0 {
+        mantissa / *power
+    } else {
+        mantissa * *power
+    })
+}
+
+/// Parse a float, rounding correctly
+pub fn parse_float(value: &str) -> Result<f32, ()> {
+    parse(value)
+}
+
+/// Parse a double, rounding correctly
+pub fn parse_double(value: &str) -> Result<f64, ()> {
+    parse(value)
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::{parse_double, parse_float};
+    use std::format;
+    use std::string::String;
+
+    /// Whether a number is parsed exactly as `core` does, with overflow being an error
+    fn same_f32(s: &str) -> bool {
+        let expected: f32 = s.parse().unwrap();
+        let expected = Some(expected).filter(|value| value.is_finite()).ok_or(());
+        parse_float(s).map(f32::to_bits) == expected.map(f32::to_bits)
+    }
+
+    /// Whether a number is parsed exactly as `core` does, with overflow being an error
+    fn same_f64(s: &str) -> bool {
+        let expected: f64 = s.parse().unwrap();
+        let expected = Some(expected).filter(|value| value.is_finite()).ok_or(());
+        parse_double(s).map(f64::to_bits) == expected.map(f64::to_bits)
+    }
+
+    #[test]
+    fn errors() {
+        for s in [
+            "", "-", ".", "-.", "e5", "1e", "1e+", "1.2.3", "1e5.0", "1x", "+1", "--1",
+        ] {
+            assert!(parse_float(s).is_err(), "{}", s);
+            assert!(parse_double(s).is_err(), "{}", s);
+        }
+        assert!(parse_float("3.5e38").is_err());
+        assert!(parse_double("1.8e308").is_err());
+        assert!(parse_double("1e99999999999999").is_err());
+    }
+
+    #[test]
+    fn special_cases() {
+        for s in [
+            "0",
+            "-0",
+            "0e99999999999999",
+            "1e-99999999999999",
+            "0.3",
+            "5.",
+            ".5",
+            "000123.456000",
+            // Halfway between two floats, so rounding to even
+            "9007199254740993",
+            "9007199254740995",
+            "16777217",
+            "16777219",
+            "1.00000000000000011102230246251565404236316680908203125",
+            "1.00000005960464477539062500",
+            // Just either side of halfway
+            "9007199254740993.0000000000000000000001",
+            "9007199254740992.9999999999999999999999",
+            "1.000000059604644775390624999999999999",
+            "1.000000059604644775390625000000000001",
+            // The limits of the normal and subnormal ranges
+            "2.2250738585072011e-308",
+            "2.2250738585072014e-308",
+            "4.9406564584124654e-324",
+            "2.4703282292062327e-324",
+            "2.4703282292062328e-324",
+            "1.7976931348623157e308",
+            "1.7976931348623158079e308",
+            "1.1754943508222875e-38",
+            "1.401298464324817e-45",
+            "7.006492321624085e-46",
+            "3.4028234663852886e38",
+            "3.40282356779733661637539395458142568447e38",
+        ] {
+            assert!(same_f32(s), "{}", s);
+            assert!(same_f64(s), "{}", s);
+        }
+        // Digits beyond those that are stored can still affect the rounding
+        let halfway = "1.00000000000000011102230246251565404236316680908203125";
+        let long = format!("{}{}1", halfway, "0".repeat(1000));
+        assert!(same_f64(&long));
+        assert_eq!(parse_double(&long), Ok(1.0000000000000002));
+        let long = format!("0.{}1e10", "0".repeat(1000));
+        assert!(same_f32(&long));
+        assert!(same_f64(&long));
+    }
+
+    #[test]
+    fn limits() {
+        // The fallback's buffer is the bulk of its stack use
+        assert!(core::mem::size_of::<super::Decimal>() < 800);
+        // Numbers far longer than the digits kept, at the ends of the range, still round correctly
+        let digits = "1234567890".repeat(10_000);
+        for s in [
+            format!("1.{}e-308", digits),
+            format!("2.4703282292062327{}e-324", digits),
+            format!("1.7976931348623157{}e308", digits),
+            format!("0.{}{}e-290", "0".repeat(50_000), digits),
+            format!("{}e-99999", digits),
+        ] {
+            assert!(same_f64(&s), "{}", &s[..40]);
+            assert!(same_f32(&s), "{}", &s[..40]);
+        }
+    }
+
+    #[test]
+    fn random_doubles() {
+        // xorshift64* gives a spread of bit patterns
+        let mut seed: u64 = 0x5eed;
+        for _ in 0..5000 {
+            seed ^= seed >> 12;
+            seed ^= seed << 25;
+            seed ^= seed >> 27;
+            let value = f64::from_bits(seed.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 1);
+            if !value.is_finite() {
+                continue;
+            }
+            for s in [
+                format!("{:e}", value),
+                format!("{:.5e}", value),
+                format!("{:.16e}", value),
+                format!("{:.40e}", value),
+            ] {
+                assert!(same_f64(&s), "{}", s);
+            }
+        }
+    }
+
+    #[test]
+    fn every_few_floats() {
+        // Exact decimal expansions of each float and the midpoints between neighbouring floats,
+        // which are the hardest to round correctly
+        for bits in (0..0x7f80_0000u32).step_by(40009) {
+            let value = f32::from_bits(bits);
+            let next = f32::from_bits(bits + 1);
+            let midpoint = (value as f64 + next as f64) / 2.;
+            let exact: String = format!("{:.200e}", value as f64);
+            let halfway: String = format!("{:.200e}", midpoint);
+            assert!(same_f32(&exact), "{}", exact);
+            assert!(same_f32(&halfway), "{}", halfway);
+            assert!(same_f32(&format!("{:e}", value)), "{}", value);
+            assert!(same_f64(&halfway), "{}", halfway);
+        }
+    }
+}
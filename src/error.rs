@@ -1,9 +1,8 @@
-/// Errors while parsing JSON
+/// The different kinds of error that can occur while parsing JSON
 ///
-/// Due to the "scan once" philosophy of this crate, errors can either be returned when first
-/// constructing a [`JSONValue`] or when trying to read it using one of the accessors.
+/// See [`JSONParsingError::kind`].
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum JSONParsingError {
+pub enum JSONParsingErrorKind {
     /// Attempt to parse an object that is not an array as an array
     CannotParseArray,
     /// Attempt to parse an object that is not a float as a float
@@ -16,8 +15,25 @@ pub enum JSONParsingError {
     CannotParseString,
     /// Attempt to parse an object that is not a boolean as a boolean
     CannotParseBoolean,
+    /// Attempt to parse an object that is not a number as a number
+    CannotParseNumber,
+    /// The number is too large or small for the requested type
+    NumberOutOfRange,
+    /// An integer was requested, but the number has a fractional part, or is written with a
+    /// fractional part or exponent without
+    /// [`ParseOptions::with_integral_floats`](crate::ParseOptions::with_integral_floats)
+    NotAnInteger,
     /// The key is not present in the object
     KeyNotFound,
+    /// The key appears more than once in the object
+    DuplicateKey,
+    /// The index is past the end of the array
+    IndexOutOfRange,
+    /// Nothing was found at a path, because the segment with this index (counting from zero)
+    /// could not be followed
+    PathNotFound(usize),
+    /// The path is malformed at this byte position
+    InvalidPath(usize),
     /// There was an unexpected token in the input stream
     UnexpectedToken,
     /// The input stream terminated while scanning a type
@@ -26,16 +42,44 @@ pub enum JSONParsingError {
     TooShortEscapeSequence,
     /// Escape sequence doesn't map to a character
     InvalidUnicodeEscapeSequence,
+    /// Escape sequence is half of a UTF-16 surrogate pair, but the other half is missing
+    LoneSurrogate,
     /// Escape pattern (\x) doesn't make sense
     InvalidEscapeSequence(char),
+    /// Arrays and objects are nested more deeply than allowed by the
+    /// [`ParseOptions`](crate::ParseOptions)
+    DepthLimitExceeded,
+    /// The buffer provided is too small, and needs to be at least this long
+    BufferTooSmall(usize),
+    /// The value, or query path, is too long for offsets within it to be stored as `u32`
+    ValueTooLong,
 }
 
-impl core::fmt::Display for JSONParsingError {
+impl JSONParsingErrorKind {
+    /// Attach a byte offset to this kind of error
+    pub(crate) fn at(self, offset: usize) -> JSONParsingError {
+        JSONParsingError { kind: self, offset }
+    }
+}
+
+impl core::fmt::Display for JSONParsingErrorKind {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match *self {
             Self::KeyNotFound => {
                 write!(f, "key not found")
             }
+            Self::DuplicateKey => {
+                write!(f, "duplicate key")
+            }
+            Self::IndexOutOfRange => {
+                write!(f, "index out of range")
+            }
+            Self::PathNotFound(segment) => {
+                write!(f, "path not found at segment {}", segment)
+            }
+            Self::InvalidPath(position) => {
+                write!(f, "invalid path at position {}", position)
+            }
             Self::EndOfStream => {
                 write!(f, "stream ended while parsing JSON")
             }
@@ -60,16 +104,106 @@ impl core::fmt::Display for JSONParsingError {
             Self::CannotParseObject => {
                 write!(f, "error parsing object")
             }
+            Self::CannotParseNumber => {
+                write!(f, "error parsing number")
+            }
+            Self::NumberOutOfRange => {
+                write!(f, "number out of range")
+            }
+            Self::NotAnInteger => {
+                write!(f, "number is not an integer")
+            }
             Self::TooShortEscapeSequence => {
                 write!(f, "escape sequence fewer than four digits")
             }
             Self::InvalidUnicodeEscapeSequence => {
                 write!(f, "escape sequence doesn't map to a character")
             }
+            Self::LoneSurrogate => {
+                write!(f, "escape sequence is an unpaired UTF-16 surrogate")
+            }
             Self::InvalidEscapeSequence(x) => {
                 write!(f, "invalid escape sequence \"\\{}\"", x)
             }
+            Self::DepthLimitExceeded => {
+                write!(f, "nesting depth limit exceeded")
+            }
+            Self::BufferTooSmall(needed) => {
+                write!(f, "buffer too small, a length of {} is needed", needed)
+            }
+            Self::ValueTooLong => {
+                write!(f, "value too long to index")
+            }
+        }
+    }
+}
+
+/// Errors while parsing JSON
+///
+/// Due to the "scan once" philosophy of this crate, errors can either be returned when first
+/// constructing a [`JSONValue`](crate::JSONValue) or when trying to read it using one of the
+/// accessors.
+///
+/// Every error records the byte offset into the original input at which it was detected. Use
+/// [`JSONParsingError::line_column`] to turn this into a human readable position.
+///
+/// ### Example
+/// ```
+/// # use microjson::{JSONValue, JSONParsingErrorKind};
+/// let input = "{\n  \"a\": [1, 2,, 3]\n}";
+/// let error = JSONValue::load_and_verify(input).unwrap_err();
+/// assert_eq!(error.kind(), JSONParsingErrorKind::UnexpectedToken);
+/// assert_eq!(error.offset(), 15);
+/// assert_eq!(error.line_column(input), (2, 14));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct JSONParsingError {
+    kind: JSONParsingErrorKind,
+    offset: usize,
+}
+
+impl JSONParsingError {
+    /// Construct an error of the given kind at a byte offset into the input
+    pub fn new(kind: JSONParsingErrorKind, offset: usize) -> Self {
+        JSONParsingError { kind, offset }
+    }
+
+    /// What went wrong
+    pub fn kind(&self) -> JSONParsingErrorKind {
+        self.kind
+    }
+
+    /// The byte offset into the original input at which the error was detected
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Convert the offset of this error into a line and column in `input`
+    ///
+    /// `input` should be the string originally passed to [`JSONValue::load`](crate::JSONValue::load).
+    /// Both the line and column are one-based, and the column counts characters rather than
+    /// bytes. Offsets past the end of the input are clamped to the end.
+    pub fn line_column(&self, input: &str) -> (usize, usize) {
+        let mut line = 1;
+        let mut column = 1;
+        for (index, chr) in input.char_indices() {
+            if index >= self.offset {
+                break;
+            }
+            if chr == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
         }
+        (line, column)
+    }
+}
+
+impl core::fmt::Display for JSONParsingError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.offset)
     }
 }
 
@@ -86,19 +220,51 @@ mod test {
     fn error_formatting() {
         // This is mostly to check the formatting doesn't crash or overlap, rather than the format exactly
         let mut messages = std::collections::HashSet::new();
-        messages.insert(JSONParsingError::CannotParseArray.to_string());
-        messages.insert(JSONParsingError::CannotParseFloat.to_string());
-        messages.insert(JSONParsingError::CannotParseInteger.to_string());
-        messages.insert(JSONParsingError::CannotParseObject.to_string());
-        messages.insert(JSONParsingError::CannotParseString.to_string());
-        messages.insert(JSONParsingError::CannotParseBoolean.to_string());
-        messages.insert(JSONParsingError::KeyNotFound.to_string());
-        messages.insert(JSONParsingError::UnexpectedToken.to_string());
-        messages.insert(JSONParsingError::EndOfStream.to_string());
-        messages.insert(JSONParsingError::TooShortEscapeSequence.to_string());
-        messages.insert(JSONParsingError::InvalidUnicodeEscapeSequence.to_string());
-        messages.insert(JSONParsingError::InvalidEscapeSequence('q').to_string());
-        messages.insert(JSONParsingError::InvalidEscapeSequence('v').to_string());
-        assert_eq!(messages.len(), 13);
+        messages.insert(JSONParsingErrorKind::CannotParseArray.to_string());
+        messages.insert(JSONParsingErrorKind::CannotParseFloat.to_string());
+        messages.insert(JSONParsingErrorKind::CannotParseInteger.to_string());
+        messages.insert(JSONParsingErrorKind::CannotParseObject.to_string());
+        messages.insert(JSONParsingErrorKind::CannotParseString.to_string());
+        messages.insert(JSONParsingErrorKind::CannotParseBoolean.to_string());
+        messages.insert(JSONParsingErrorKind::CannotParseNumber.to_string());
+        messages.insert(JSONParsingErrorKind::NumberOutOfRange.to_string());
+        messages.insert(JSONParsingErrorKind::NotAnInteger.to_string());
+        messages.insert(JSONParsingErrorKind::KeyNotFound.to_string());
+        messages.insert(JSONParsingErrorKind::DuplicateKey.to_string());
+        messages.insert(JSONParsingErrorKind::IndexOutOfRange.to_string());
+        messages.insert(JSONParsingErrorKind::PathNotFound(0).to_string());
+        messages.insert(JSONParsingErrorKind::PathNotFound(3).to_string());
+        messages.insert(JSONParsingErrorKind::InvalidPath(3).to_string());
+        messages.insert(JSONParsingErrorKind::UnexpectedToken.to_string());
+        messages.insert(JSONParsingErrorKind::EndOfStream.to_string());
+        messages.insert(JSONParsingErrorKind::TooShortEscapeSequence.to_string());
+        messages.insert(JSONParsingErrorKind::InvalidUnicodeEscapeSequence.to_string());
+        messages.insert(JSONParsingErrorKind::LoneSurrogate.to_string());
+        messages.insert(JSONParsingErrorKind::InvalidEscapeSequence('q').to_string());
+        messages.insert(JSONParsingErrorKind::InvalidEscapeSequence('v').to_string());
+        messages.insert(JSONParsingErrorKind::DepthLimitExceeded.to_string());
+        messages.insert(JSONParsingErrorKind::BufferTooSmall(7).to_string());
+        messages.insert(JSONParsingErrorKind::ValueTooLong.to_string());
+        assert_eq!(messages.len(), 25);
+
+        assert_eq!(
+            JSONParsingErrorKind::EndOfStream.at(12).to_string(),
+            "stream ended while parsing JSON at byte 12"
+        );
+    }
+
+    #[test]
+    fn line_column() {
+        let input = "[\n  1,\n  £, 2]";
+        let position = |offset| {
+            JSONParsingError::new(JSONParsingErrorKind::UnexpectedToken, offset).line_column(input)
+        };
+        assert_eq!(position(0), (1, 1));
+        assert_eq!(position(1), (1, 2));
+        assert_eq!(position(2), (2, 1));
+        assert_eq!(position(9), (3, 3));
+        // The pound sign is two bytes long but only one column wide
+        assert_eq!(position(11), (3, 4));
+        assert_eq!(position(100), (3, 8));
     }
 }
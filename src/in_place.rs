@@ -0,0 +1,502 @@
+//! Reading JSON from a mutable buffer, decoding strings in place
+//!
+//! Decoding an escape sequence never makes a string longer, so the decoded string can be written
+//! over the escaped one without needing another buffer. Arrays and objects hand out their items
+//! as disjoint parts of the buffer, so that every decoded string can be kept at once.
+
+use crate::{
+    EscapedStringIterator, JSONArrayIterator, JSONObjectIterator, JSONParsingError,
+    JSONParsingErrorKind, JSONValue, JSONValueType, ParseOptions,
+};
+
+/// The longest escape sequence, a UTF-16 surrogate pair
+const MAX_ESCAPE_LEN: usize = 12;
+
+/// View a buffer as a string
+///
+/// The buffer is checked when first loaded, and is only ever split between characters, so this
+/// does not fail.
+fn as_str(buffer: &[u8]) -> &str {
+    core::str::from_utf8(buffer).unwrap_or_default()
+}
+
+/// Where a value read from a buffer is, so that it can be split off
+#[derive(Copy, Clone)]
+struct Span {
+    offset: usize,
+    len: usize,
+    depth: usize,
+    options: ParseOptions,
+    value_type: JSONValueType,
+}
+
+impl Span {
+    fn of(value: &JSONValue) -> Span {
+        Span {
+            offset: value.offset,
+            len: value.contents.len(),
+            depth: value.depth,
+            options: value.options,
+            value_type: value.value_type,
+        }
+    }
+}
+
+/// A JSON value in a mutable buffer, whose strings can be decoded in place
+///
+/// This is like [`JSONValue`], but reading a string with [`JSONValueMut::read_string_in_place`]
+/// decodes its escape sequences into the buffer and borrows the result, rather than copying it
+/// out. Reading is destructive, so the methods that read a string, array or object consume the
+/// value. Use [`JSONValueMut::as_value`] for everything else.
+///
+/// ### Example
+/// ```
+/// # use microjson::{JSONValueMut, JSONParsingError};
+/// # fn main() -> Result<(), JSONParsingError> {
+/// let mut buffer = *br#"{"name": "\"Fiddler\" crab", "legs": 10}"#;
+/// let mut name = "";
+/// let mut legs = 0;
+/// for entry in JSONValueMut::load(&mut buffer)?.iter_object()? {
+///     let (key, value) = entry?;
+///     match key {
+///         "name" => name = value.read_string_in_place()?,
+///         "legs" => legs = value.as_value().read_integer()?,
+///         _ => {}
+///     }
+/// }
+/// assert_eq!(name, "\"Fiddler\" crab");
+/// assert_eq!(legs, 10);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct JSONValueMut<'a> {
+    buffer: &'a mut [u8],
+    /// Byte offset of `buffer` in the original input, used for error reporting
+    offset: usize,
+    /// The number of arrays and objects enclosing this value
+    depth: usize,
+    options: ParseOptions,
+    pub value_type: JSONValueType,
+}
+
+impl<'a> JSONValueMut<'a> {
+    /// Create a new `JSONValueMut` from an input buffer
+    ///
+    /// Like [`JSONValue::load`], this does not check that the buffer holds valid JSON, and only
+    /// fails if it is not valid UTF-8. To use options other than the defaults, see
+    /// [`ParseOptions::load_mut`].
+    pub fn load(buffer: &'a mut [u8]) -> Result<JSONValueMut<'a>, JSONParsingError> {
+        ParseOptions::new().load_mut(buffer)
+    }
+
+    pub(crate) fn load_with_options(
+        buffer: &'a mut [u8],
+        options: ParseOptions,
+    ) -> Result<JSONValueMut<'a>, JSONParsingError> {
+        if let Err(error) = core::str::from_utf8(buffer) {
+            return Err(JSONParsingErrorKind::UnexpectedToken.at(error.valid_up_to()));
+        }
+        let value = JSONValue::load_with_options(as_str(buffer), options);
+        let (start, value_type) = (value.offset, value.value_type);
+        Ok(JSONValueMut {
+            buffer: &mut buffer[start..],
+            offset: start,
+            depth: 0,
+            options,
+            value_type,
+        })
+    }
+
+    /// The part of `buffer` holding a value, where `buffer` is at `offset` in the input
+    fn from_span(buffer: &'a mut [u8], offset: usize, span: Span) -> JSONValueMut<'a> {
+        let start = span.offset - offset;
+        JSONValueMut {
+            buffer: &mut buffer[start..start + span.len],
+            offset: span.offset,
+            depth: span.depth,
+            options: span.options,
+            value_type: span.value_type,
+        }
+    }
+
+    /// A read only view of this value
+    pub fn as_value(&self) -> JSONValue<'_> {
+        JSONValue {
+            contents: as_str(self.buffer),
+            offset: self.offset,
+            depth: self.depth,
+            options: self.options,
+            value_type: self.value_type,
+        }
+    }
+
+    /// Convert this into a read only value
+    pub fn into_value(self) -> JSONValue<'a> {
+        let buffer: &'a [u8] = self.buffer;
+        JSONValue {
+            contents: as_str(buffer),
+            offset: self.offset,
+            depth: self.depth,
+            options: self.options,
+            value_type: self.value_type,
+        }
+    }
+
+    /// Read the [`JSONValueMut`] as a string, decoding any escape sequences in place
+    ///
+    /// If the type is not a [`JSONValueType::String`], returns an `Err`. Escape sequences are
+    /// handled as for [`JSONValue::iter_string`], and the buffer is left in an unspecified state
+    /// if one is malformed.
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::{JSONValueMut, JSONParsingError};
+    /// # fn main() -> Result<(), JSONParsingError> {
+    /// let mut buffer = *br#" "tab\tseparated \u2192" "#;
+    /// let value = JSONValueMut::load(&mut buffer)?;
+    /// assert_eq!(value.read_string_in_place()?, "tab\tseparated →");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_string_in_place(self) -> Result<&'a str, JSONParsingError> {
+        let end = 1 + self.as_value().read_string()?.len();
+        let quote = self.buffer[0] as char;
+        let offset = self.offset;
+        let buffer = self.buffer;
+        // Decoded characters are written to the start of the string, behind those being read
+        let mut read = 1;
+        let mut write = 1;
+        loop {
+            let escape = buffer[read..end]
+                .iter()
+                .position(|&b| b == b'\\')
+                .map_or(end, |len| read + len);
+            buffer.copy_within(read..escape, write);
+            write += escape - read;
+            read = escape;
+            if read == end {
+                break;
+            }
+
+            // Decode a single escape sequence, which may need to see the closing quote
+            let window = &buffer[read..usize::min(read + MAX_ESCAPE_LEN, end + 1)];
+            let window = match core::str::from_utf8(window) {
+                Ok(window) => window,
+                Err(error) => as_str(&window[..error.valid_up_to()]),
+            };
+            let mut chars = EscapedStringIterator::new(window, offset + read, quote, self.options);
+            let chr = match chars.read_char() {
+                Some(chr) => chr?,
+                None => return Err(JSONParsingErrorKind::EndOfStream.at(offset + read)),
+            };
+            read += window.len() - chars.contents.as_str().len();
+            if let Some(chr) = chr {
+                let mut encoded = [0; 4];
+                let encoded = chr.encode_utf8(&mut encoded).as_bytes();
+                buffer[write..write + encoded.len()].copy_from_slice(encoded);
+                write += encoded.len();
+            }
+        }
+        let buffer: &'a [u8] = buffer;
+        core::str::from_utf8(&buffer[1..write])
+            .map_err(|_| JSONParsingErrorKind::CannotParseString.at(offset))
+    }
+
+    /// Constructs an iterator over this array value
+    ///
+    /// If the value is not an [`JSONValueType::Array`], returns an error.
+    pub fn iter_array(self) -> Result<JSONArrayIteratorMut<'a>, JSONParsingError> {
+        self.as_value().iter_array()?;
+        Ok(JSONArrayIteratorMut {
+            rest: self.buffer.get_mut(1..).unwrap_or_default(),
+            offset: self.offset + 1,
+            depth: self.depth + 1,
+            options: self.options,
+        })
+    }
+
+    /// Constructs an iterator over this object value, whose keys are decoded in place
+    ///
+    /// If the value is not an [`JSONValueType::Object`], returns an error.
+    pub fn iter_object(self) -> Result<JSONObjectIteratorMut<'a>, JSONParsingError> {
+        self.as_value().iter_object()?;
+        Ok(JSONObjectIteratorMut {
+            rest: self.buffer.get_mut(1..).unwrap_or_default(),
+            offset: self.offset + 1,
+            depth: self.depth + 1,
+            options: self.options,
+        })
+    }
+
+    /// Searches this object for a key and returns it's value
+    ///
+    /// Keys are compared after decoding their escape sequences. As with
+    /// [`JSONValue::get_key_value`], will return an error of kind
+    /// [`JSONParsingErrorKind::KeyNotFound`] if the key is not present.
+    pub fn get_key_value(self, key: &str) -> Result<JSONValueMut<'a>, JSONParsingError> {
+        let offset = self.offset;
+        for item in self.iter_object()? {
+            let (k, value) = item?;
+            if k == key {
+                return Ok(value);
+            }
+        }
+        Err(JSONParsingErrorKind::KeyNotFound.at(offset))
+    }
+}
+
+/// An iterator through a JSON array value in a mutable buffer
+///
+/// Usually constructed with [`JSONValueMut::iter_array`].
+#[derive(Debug)]
+pub struct JSONArrayIteratorMut<'a> {
+    rest: &'a mut [u8],
+    /// Byte offset of `rest` in the original input
+    offset: usize,
+    /// The depth of the values in the array
+    depth: usize,
+    options: ParseOptions,
+}
+
+impl<'a> Iterator for JSONArrayIteratorMut<'a> {
+    type Item = JSONValueMut<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Find the next item with a read only iterator, then split it off the buffer
+        let contents = as_str(self.rest);
+        let mut items = JSONArrayIterator {
+            contents,
+            offset: self.offset,
+            depth: self.depth,
+            options: self.options,
+        };
+        let item = Span::of(&items.next()?);
+        let consumed = contents.len() - items.contents.len();
+
+        let (head, rest) = core::mem::take(&mut self.rest).split_at_mut(consumed);
+        let item = JSONValueMut::from_span(head, self.offset, item);
+        self.rest = rest;
+        self.offset += consumed;
+        Some(item)
+    }
+}
+
+/// An iterator through a JSON object in a mutable buffer
+///
+/// Usually constructed with [`JSONValueMut::iter_object`].
+///
+/// The iterator items are `Result<(key, value), JSONParsingError>`, and the keys are decoded in
+/// place.
+#[derive(Debug)]
+pub struct JSONObjectIteratorMut<'a> {
+    rest: &'a mut [u8],
+    /// Byte offset of `rest` in the original input
+    offset: usize,
+    /// The depth of the values in the object
+    depth: usize,
+    options: ParseOptions,
+}
+
+impl<'a> JSONObjectIteratorMut<'a> {
+    fn read_entry(&mut self) -> Option<Result<(&'a str, JSONValueMut<'a>), JSONParsingError>> {
+        // Find the next entry with a read only iterator, then split it off the buffer
+        let contents = as_str(self.rest);
+        let mut entries = JSONObjectIterator {
+            contents,
+            offset: self.offset,
+            depth: self.depth,
+            options: self.options,
+        };
+        let (key, value) = match entries.next()? {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e)),
+        };
+        let key_start = key.as_ptr() as usize - contents.as_ptr() as usize;
+        let key_end = key_start + key.len();
+        // JSON5 keys may be unquoted, in which case there is nothing to decode
+        let quoted = key_start > 0 && matches!(contents.as_bytes()[key_start - 1], b'"' | b'\'');
+        let value = Span::of(&value);
+        let consumed = contents.len() - entries.contents.len();
+
+        let (head, rest) = core::mem::take(&mut self.rest).split_at_mut(consumed);
+        self.rest = rest;
+        let offset = self.offset;
+        self.offset += consumed;
+        let (head, value_buffer) = head.split_at_mut(value.offset - offset);
+        let value = JSONValueMut::from_span(value_buffer, value.offset, value);
+        let key = if quoted {
+            let key = JSONValueMut {
+                buffer: &mut head[key_start - 1..key_end + 1],
+                offset: offset + key_start - 1,
+                depth: self.depth,
+                options: self.options,
+                value_type: JSONValueType::String,
+            };
+            match key.read_string_in_place() {
+                Ok(key) => key,
+                Err(e) => return Some(Err(e)),
+            }
+        } else {
+            let head: &'a [u8] = head;
+            as_str(&head[key_start..key_end])
+        };
+        Some(Ok((key, value)))
+    }
+}
+
+impl<'a> Iterator for JSONObjectIteratorMut<'a> {
+    type Item = Result<(&'a str, JSONValueMut<'a>), JSONParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.read_entry();
+        if let Some(Err(_)) = entry {
+            self.rest = &mut [];
+        }
+        entry
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use std::string::String;
+    use std::vec::Vec;
+
+    /// Decode a string both in place and with `iter_string`
+    fn decode(input: &str, options: ParseOptions) -> (Result<String, JSONParsingError>, String) {
+        let expected = options
+            .load(input)
+            .iter_string()
+            .unwrap()
+            .collect::<Result<String, _>>();
+        let mut buffer: Vec<u8> = input.into();
+        let decoded = options
+            .load_mut(&mut buffer)
+            .unwrap()
+            .read_string_in_place()
+            .map(String::from);
+        (
+            expected,
+            decoded.unwrap_or_else(|e| std::format!("{:?}", e)),
+        )
+    }
+
+    #[test]
+    fn strings() {
+        for input in [
+            r#""""#,
+            r#""no escapes""#,
+            r#" "\"\\\/\b\f\n\r\t" "#,
+            r#""été ⟼""#,
+            r#""😀𝄞""#,
+            r#""😀 A£""#,
+            r#""\n""#,
+        ] {
+            let (expected, decoded) = decode(input, ParseOptions::new());
+            assert_eq!(expected.unwrap(), decoded, "{}", input);
+        }
+        for input in [
+            r#""\q""#,
+            r#""abc\u12""#,
+            r#""\ud83dx""#,
+            r#""😀\ude00""#,
+            r#""\"#,
+        ] {
+            let (expected, decoded) = decode(input, ParseOptions::new());
+            assert_eq!(std::format!("{:?}", expected.unwrap_err()), decoded);
+        }
+        // Unterminated strings fail as they do for `read_string`
+        let mut buffer = *br#""\u00"#;
+        assert_eq!(
+            JSONValueMut::load(&mut buffer)
+                .unwrap()
+                .read_string_in_place(),
+            Err(JSONParsingErrorKind::EndOfStream.at(5))
+        );
+        let mut buffer = *b"12";
+        assert_eq!(
+            JSONValueMut::load(&mut buffer)
+                .unwrap()
+                .read_string_in_place()
+                .map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::CannotParseString)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json5")]
+    fn json5_strings() {
+        let options = ParseOptions::new().with_json5(true);
+        for input in [
+            r#"'single "quoted"'"#,
+            r#"'\x41\v\0\'\a'"#,
+            "'line \\\ncontinued \\\r\nagain \\\u{2028}!'",
+        ] {
+            let (expected, decoded) = decode(input, options);
+            assert_eq!(expected.unwrap(), decoded, "{}", input);
+        }
+    }
+
+    #[test]
+    fn navigation() {
+        let mut buffer: Vec<u8> = r#" {"a\tb": ["x\n", 1, {"key": "é"}], "c": null} "#.into();
+        let mut entries = JSONValueMut::load(&mut buffer)
+            .unwrap()
+            .iter_object()
+            .unwrap();
+        let (key, array) = entries.next().unwrap().unwrap();
+        let (last_key, null) = entries.next().unwrap().unwrap();
+        assert!(entries.next().is_none());
+        assert!(null.as_value().is_null());
+
+        let mut items = array.iter_array().unwrap();
+        let string = items.next().unwrap();
+        let number = items.next().unwrap().into_value();
+        let object = items.next().unwrap();
+        assert!(items.next().is_none());
+        let value = object.get_key_value("key").unwrap();
+
+        // All of the decoded strings can be held at once
+        assert_eq!(key, "a\tb");
+        assert_eq!(last_key, "c");
+        assert_eq!(string.read_string_in_place(), Ok("x\n"));
+        assert_eq!(number.read_integer(), Ok(1));
+        assert_eq!(value.read_string_in_place(), Ok("é"));
+    }
+
+    #[test]
+    fn errors() {
+        let mut buffer = *b"[1, \xff]";
+        assert_eq!(
+            JSONValueMut::load(&mut buffer).unwrap_err(),
+            JSONParsingErrorKind::UnexpectedToken.at(4)
+        );
+
+        let mut buffer = *br#"{"a": 1, "\q": 2}"#;
+        let mut entries = JSONValueMut::load(&mut buffer)
+            .unwrap()
+            .iter_object()
+            .unwrap();
+        assert_eq!(entries.next().unwrap().unwrap().0, "a");
+        assert_eq!(
+            entries.next().unwrap().unwrap_err(),
+            JSONParsingErrorKind::InvalidEscapeSequence('q').at(10)
+        );
+        assert!(entries.next().is_none());
+
+        let mut buffer = *br#"{"a": 1}"#;
+        let value = JSONValueMut::load(&mut buffer).unwrap();
+        assert_eq!(
+            value.get_key_value("b").unwrap_err(),
+            JSONParsingErrorKind::KeyNotFound.at(0)
+        );
+        let mut buffer = *br#"{"a": 1}"#;
+        let value = JSONValueMut::load(&mut buffer).unwrap();
+        assert_eq!(
+            value.iter_array().unwrap_err().kind(),
+            JSONParsingErrorKind::CannotParseArray
+        );
+    }
+}
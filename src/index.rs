@@ -0,0 +1,164 @@
+//! Structural indices of arrays and objects, for reading their items in any order
+//!
+//! An index holds the byte offset of each item of an array, or each key of an object, so that
+//! reading an item does not mean skipping over all those before it.  The offsets of an object
+//! are sorted by key, so that keys can be found by binary search.
+
+use crate::{
+    JSONObjectIterator, JSONParsingError, JSONParsingErrorKind, JSONValue, JSONValueType, ObjectKey,
+};
+use core::convert::TryFrom;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// An index of the items of an array or the entries of an object
+///
+/// This gives ordinary [`JSONValue`]s, reading array items by position in constant time and
+/// object values by key in logarithmic time.  The offsets are kept in `T`, which is a slice of a
+/// buffer for [`JSONValue::index_into`], or a `Vec` for `JSONValue::index` with the `alloc`
+/// feature.
+#[derive(Clone, Debug)]
+pub struct JSONIndex<'a, T> {
+    value: JSONValue<'a>,
+    /// The byte offset, after the opening bracket of `value`, of each item or key.  Object keys
+    /// are sorted, with duplicate keys in the order they appear.
+    offsets: T,
+}
+
+impl<'a, 'b> JSONIndex<'a, &'b [u32]> {
+    /// Index `value`, storing the offsets at the start of `buffer`
+    pub(crate) fn new_in(
+        value: JSONValue<'a>,
+        buffer: &'b mut [u32],
+    ) -> Result<Self, JSONParsingError> {
+        let len = value.len()?;
+        if len > buffer.len() {
+            return Err(JSONParsingErrorKind::BufferTooSmall(len).at(value.offset));
+        }
+        let offsets = &mut buffer[..len];
+        fill(value, offsets)?;
+        Ok(JSONIndex { value, offsets })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> JSONIndex<'a, Vec<u32>> {
+    /// Index `value`, storing the offsets in a new `Vec`
+    pub(crate) fn new(value: JSONValue<'a>) -> Result<Self, JSONParsingError> {
+        let mut offsets = alloc::vec![0; value.len()?];
+        fill(value, &mut offsets)?;
+        Ok(JSONIndex { value, offsets })
+    }
+}
+
+impl<'a, T: AsRef<[u32]>> JSONIndex<'a, T> {
+    /// The number of items in the array, or entries in the object
+    pub fn len(&self) -> usize {
+        self.offsets.as_ref().len()
+    }
+
+    /// Checks if the array or object has no items
+    pub fn is_empty(&self) -> bool {
+        self.offsets.as_ref().is_empty()
+    }
+
+    /// Reads the item at `index` in the array
+    ///
+    /// If the indexed value is not an [`JSONValueType::Array`], returns an error, and if the array
+    /// is too short, returns an error of kind [`JSONParsingErrorKind::IndexOutOfRange`].
+    pub fn get_index(&self, index: usize) -> Result<JSONValue<'a>, JSONParsingError> {
+        if self.value.value_type != JSONValueType::Array {
+            return Err(JSONParsingErrorKind::CannotParseArray.at(self.value.offset));
+        }
+        let start = self
+            .offsets
+            .as_ref()
+            .get(index)
+            .ok_or_else(|| JSONParsingErrorKind::IndexOutOfRange.at(self.value.offset))?;
+        self.value.item_at(*start as usize)
+    }
+
+    /// Searches the object for a key and returns its value
+    ///
+    /// Keys are compared after decoding their escape sequences, and if a key appears more than
+    /// once, the first value is returned, as with [`JSONValue::get_key_value`].
+    ///
+    /// If the indexed value is not an [`JSONValueType::Object`], returns an error, and if the key
+    /// is not present, returns an error of kind [`JSONParsingErrorKind::KeyNotFound`].
+    pub fn get_key_value(&self, key: &str) -> Result<JSONValue<'a>, JSONParsingError> {
+        if self.value.value_type != JSONValueType::Object {
+            return Err(JSONParsingErrorKind::CannotParseObject.at(self.value.offset));
+        }
+        let offsets = self.offsets.as_ref();
+        let position = offsets
+            .partition_point(|&start| key_chars(read_key(self.value, start).ok()).lt(key.chars()));
+        if let Some(&start) = offsets.get(position) {
+            let mut entries = entries_at(self.value, start);
+            if let Some((found, value)) = entries.read_entry()? {
+                if found.matches(key.chars())? {
+                    return Ok(value);
+                }
+            }
+        }
+        Err(JSONParsingErrorKind::KeyNotFound.at(self.value.offset))
+    }
+}
+
+/// Store the offsets of the items of `value` in `offsets`, which has a place for each of them,
+/// sorting them by key if `value` is an object
+fn fill(value: JSONValue, offsets: &mut [u32]) -> Result<(), JSONParsingError> {
+    let object = value.value_type == JSONValueType::Object;
+    for (slot, start) in offsets.iter_mut().zip(value.item_starts()) {
+        let start = start?;
+        *slot = u32::try_from(start)
+            .map_err(|_| JSONParsingErrorKind::ValueTooLong.at(value.offset))?;
+        if object {
+            // Keys are checked here, so that comparing them while sorting and searching cannot
+            // fail
+            if let ObjectKey::Quoted(key) = read_key(value, *slot)? {
+                for chr in key.iter_string()? {
+                    chr?;
+                }
+            }
+        }
+    }
+    if object {
+        offsets.sort_unstable_by(|&a, &b| {
+            let (key_a, key_b) = (read_key(value, a).ok(), read_key(value, b).ok());
+            key_chars(key_a).cmp(key_chars(key_b)).then(a.cmp(&b))
+        });
+    }
+    Ok(())
+}
+
+/// An iterator over the entries of the object `value`, from the entry at `start`
+fn entries_at(value: JSONValue<'_>, start: u32) -> JSONObjectIterator<'_> {
+    let start = start as usize;
+    JSONObjectIterator {
+        contents: value.inner().get(start..).unwrap_or_default(),
+        offset: value.offset + 1 + start,
+        depth: value.depth + 1,
+        options: value.options,
+    }
+}
+
+/// Read the key of the entry at `start` in the object `value`
+fn read_key(value: JSONValue<'_>, start: u32) -> Result<ObjectKey<'_>, JSONParsingError> {
+    let entries = entries_at(value, start);
+    match entries.read_key()? {
+        Some((key, _)) => Ok(key),
+        None => Err(JSONParsingErrorKind::UnexpectedToken.at(entries.offset)),
+    }
+}
+
+/// The characters of a key that has been checked, with its escape sequences decoded
+fn key_chars(key: Option<ObjectKey<'_>>) -> impl Iterator<Item = char> + '_ {
+    let (unquoted, quoted) = match key {
+        Some(ObjectKey::Unquoted(raw)) => (Some(raw.chars()), None),
+        Some(ObjectKey::Quoted(value)) => (None, value.iter_string().ok()),
+        None => (None, None),
+    };
+    let quoted = quoted.into_iter().flatten().filter_map(Result::ok);
+    unquoted.into_iter().flatten().chain(quoted)
+}
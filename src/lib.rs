@@ -17,22 +17,45 @@
 #![doc = include_str!("../README.md")]
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, string::String};
+
 mod error;
-pub use error::JSONParsingError;
+pub use error::{JSONParsingError, JSONParsingErrorKind};
 
-#[cfg(any(feature = "small_number_parsing", test))]
-mod number_parsing;
+mod number;
+pub use number::{Decimal, JSONNumber};
+use number::{Magnitude, NumberParts};
+
+mod in_place;
+pub use in_place::{JSONArrayIteratorMut, JSONObjectIteratorMut, JSONValueMut};
+
+mod index;
+pub use index::JSONIndex;
 
-// NOTE(robert) We could override this with our own implementation, but looking at the source code,
-// it seems sufficiently compact that any gains we make will be marginal.
-static PARSE_INT: fn(&str) -> Result<isize, core::num::ParseIntError> =
-    <isize as core::str::FromStr>::from_str;
+mod options;
+pub use options::ParseOptions;
+
+mod path;
+pub use path::JSONPathIterator;
+use path::PointerToken;
+
+mod scan;
+use scan::{whitespace_len, ItemStarts};
+
+#[cfg(any(
+    all(
+        feature = "small_number_parsing",
+        not(feature = "compact_number_parsing")
+    ),
+    test
+))]
+mod number_parsing;
 
-#[cfg(not(feature = "small_number_parsing"))]
-static PARSE_FLOAT: fn(&str) -> Result<f32, core::num::ParseFloatError> =
-    <f32 as core::str::FromStr>::from_str;
-#[cfg(feature = "small_number_parsing")]
-static PARSE_FLOAT: fn(&str) -> Result<f32, ()> = number_parsing::parse_float;
+#[cfg(any(feature = "compact_number_parsing", test))]
+mod compact_number_parsing;
 
 /// Denotes the different types of values JSON objects can have
 ///
@@ -59,17 +82,14 @@ pub enum JSONValueType {
 #[derive(Copy, Clone, Debug)]
 pub struct JSONValue<'a> {
     contents: &'a str,
+    /// Byte offset of `contents` in the original input, used for error reporting
+    offset: usize,
+    /// The number of arrays and objects enclosing this value
+    depth: usize,
+    options: ParseOptions,
     pub value_type: JSONValueType,
 }
 
-fn trim_start(value: &str) -> (&str, usize) {
-    let value_len = value.len();
-    // NOTE(robert): This trims from the "start" which may be different for RTL languages.  What do
-    // we do for JSON?
-    let value = value.trim_start();
-    (value, value_len - value.len())
-}
-
 impl<'a> JSONValue<'a> {
     /// Create a new `JSONValue` from an input string
     ///
@@ -78,12 +98,22 @@ impl<'a> JSONValue<'a> {
     /// type that is not `JSONValueType::Error`.
     ///
     /// If you want to load the payload and verify that it is valid JSON, use
-    /// [`JSONValue::load_and_verify`].
-    pub fn load(contents: &'a str) -> JSONValue {
-        let (contents, _) = trim_start(contents);
-        let value_type = JSONValue::peek_value_type(contents);
+    /// [`JSONValue::load_and_verify`]. To use options other than the defaults, see
+    /// [`ParseOptions::load`].
+    pub fn load(contents: &'a str) -> JSONValue<'a> {
+        JSONValue::load_with_options(contents, ParseOptions::new())
+    }
+
+    fn load_with_options(contents: &'a str, options: ParseOptions) -> JSONValue<'a> {
+        // An unterminated comment is reported when the value is read
+        let offset = whitespace_len(contents, 0, options, false).unwrap_or(contents.len());
+        let contents = &contents[offset..];
+        let value_type = JSONValue::peek_value_type(contents, options);
         JSONValue {
             contents,
+            offset,
+            depth: 0,
+            options,
             value_type,
         }
     }
@@ -92,15 +122,17 @@ impl<'a> JSONValue<'a> {
     ///
     /// This function will never give the _wrong_ type, though it may return a type even if the
     /// input string is not well formed.
-    fn peek_value_type(contents: &'a str) -> JSONValueType {
+    fn peek_value_type(contents: &'a str, options: ParseOptions) -> JSONValueType {
         // The contents must be trimmed
         match contents.chars().next() {
             Some('{') => JSONValueType::Object,
             Some('[') => JSONValueType::Array,
             Some('"') => JSONValueType::String,
+            Some('\'') if options.json5_enabled() => JSONValueType::String,
             Some('0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | '-') => {
                 JSONValueType::Number
             }
+            Some('+' | '.' | 'I' | 'N') if options.json5_enabled() => JSONValueType::Number,
             Some('t' | 'f') => JSONValueType::Bool,
             Some('n') => JSONValueType::Null,
             _ => JSONValueType::Error,
@@ -109,8 +141,16 @@ impl<'a> JSONValue<'a> {
 
     /// Confirm that this [`JSONValue`] is proper JSON
     ///
-    /// This will scan through the entire JSON and confirm that it is properly formatted.
-    /// See also [`JSONValue::load_and_verify`].
+    /// This will scan through the entire JSON and confirm that it is properly formatted according
+    /// to the grammar in [RFC 8259](https://www.rfc-editor.org/rfc/rfc8259). In particular, numbers
+    /// must not have leading zeros, strings must not contain unescaped control characters and all
+    /// escape sequences must be valid, with UTF-16 surrogates correctly paired. Strings in a
+    /// verified value can always be read with [`JSONValue::iter_string`] without errors. If the
+    /// value was loaded with JSON5 enabled in its [`ParseOptions`], the grammar of JSON5 is used
+    /// instead. See also [`JSONValue::load_and_verify`].
+    ///
+    /// The accessors are more forgiving than this, and will happily read malformed values if they
+    /// can make sense of them.
     ///
     /// ## Example
     /// ```
@@ -120,144 +160,99 @@ impl<'a> JSONValue<'a> {
     ///
     /// let value = JSONValue::load("[,,{\"");
     /// assert!(value.verify().is_err());
+    ///
+    /// let value = JSONValue::load("[01, 2]");
+    /// assert!(value.verify().is_err());
+    /// ```
+    ///
+    /// Only whitespace may follow the value. To read a value from the start of a longer payload,
+    /// use [`JSONValue::load_prefix`].  Whitespace before the value is skipped when it is loaded,
+    /// so is checked by [`JSONValue::load_and_verify`] rather than this.
+    /// ```
+    /// # use microjson::JSONValue;
+    /// let value = JSONValue::load("[1, 2] xyz");
+    /// assert!(value.verify().is_err());
     /// ```
     pub fn verify(&self) -> Result<(), JSONParsingError> {
-        JSONValue::parse_with_len(self.contents)?;
+        let (_, value_len) =
+            JSONValue::parse_with_len(self.contents, self.offset, self.depth, self.options, true)?;
+        let rest = &self.contents[value_len..];
+        let whitespace = whitespace_len(rest, self.offset + value_len, self.options, true)?;
+        if whitespace < rest.len() {
+            return Err(
+                JSONParsingErrorKind::UnexpectedToken.at(self.offset + value_len + whitespace)
+            );
+        }
         Ok(())
     }
 
     /// Load a JSON value from a payload and verify that it is valid JSON.
     ///
     /// This is equivalent to calling [`JSONValue::load`] followed by [`JSONValue::verify`].
-    pub fn load_and_verify(contents: &'a str) -> Result<JSONValue, JSONParsingError> {
-        let value = JSONValue::load(contents);
-        value.verify()?;
-        Ok(value)
+    pub fn load_and_verify(contents: &'a str) -> Result<JSONValue<'a>, JSONParsingError> {
+        ParseOptions::new().load_and_verify(contents)
+    }
+
+    /// Load and verify the JSON value at the start of a payload, returning it along with the rest
+    /// of the payload
+    ///
+    /// Unlike [`JSONValue::load_and_verify`], anything may follow the value. This is useful for
+    /// reading a stream of concatenated values. Error offsets are relative to `contents`.
+    ///
+    /// ## Example
+    /// ```
+    /// # use microjson::{JSONValue, JSONParsingError};
+    /// # fn main() -> Result<(), JSONParsingError> {
+    /// let mut payload = r#"{"id": 1} {"id": 2}"#;
+    /// let mut ids = [0; 2];
+    /// for id in ids.iter_mut() {
+    ///     let (value, rest) = JSONValue::load_prefix(payload)?;
+    ///     *id = value.get_key_value("id")?.read_integer()?;
+    ///     payload = rest;
+    /// }
+    /// assert_eq!(ids, [1, 2]);
+    /// assert!(payload.trim().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_prefix(contents: &'a str) -> Result<(JSONValue<'a>, &'a str), JSONParsingError> {
+        ParseOptions::new().load_prefix(contents)
+    }
+
+    fn load_prefix_with_options(
+        contents: &'a str,
+        options: ParseOptions,
+    ) -> Result<(JSONValue<'a>, &'a str), JSONParsingError> {
+        let (value, value_len) = JSONValue::parse_with_len(contents, 0, 0, options, true)?;
+        Ok((value, &contents[value_len..]))
     }
 
     /// Parse a payload and return the JSONValue appearing as its prefix, along with the length of
     /// that prefix
-    fn parse_with_len(contents: &'a str) -> Result<(JSONValue, usize), JSONParsingError> {
-        let (contents, whitespace_trimmed) = trim_start(contents);
-        let (value_type, value_len) = match contents.chars().next() {
-            Some('{') => {
-                let mut value_len = 1;
-                let mut contents = &contents[value_len..];
-                while !contents.is_empty() {
-                    if contents.trim_start().starts_with('}') {
-                        value_len += trim_start(contents).1 + 1;
-                        break;
-                    }
-                    let (item, item_len) = JSONValue::parse_with_len(contents)?;
-                    if item.value_type != JSONValueType::String {
-                        return Err(JSONParsingError::CannotParseString);
-                    }
-                    let (new_contents, whitespace) = trim_start(&contents[item_len..]);
-                    contents = new_contents;
-                    value_len += item_len + whitespace;
-                    if contents.is_empty() {
-                        return Err(JSONParsingError::EndOfStream);
-                    } else if contents.starts_with(':') {
-                        value_len += 1;
-                        contents = &contents[1..];
-                    } else {
-                        return Err(JSONParsingError::UnexpectedToken);
-                    }
-
-                    let (_, item_len) = JSONValue::parse_with_len(contents)?;
-                    let (new_contents, whitespace) = trim_start(&contents[item_len..]);
-                    contents = new_contents;
-                    value_len += item_len + whitespace;
-                    if contents.is_empty() {
-                        return Err(JSONParsingError::EndOfStream);
-                    } else if contents.starts_with(',') {
-                        value_len += 1;
-                        contents = &contents[1..];
-                    } else if !contents.starts_with('}') {
-                        return Err(JSONParsingError::UnexpectedToken);
-                    }
-                }
-                (JSONValueType::Object, value_len)
-            }
-            Some('[') => {
-                let mut value_len = 1;
-                let mut contents = &contents[value_len..];
-                while !contents.is_empty() {
-                    if contents.trim_start().starts_with(']') {
-                        value_len += trim_start(contents).1 + 1;
-                        break;
-                    }
-                    let (_, item_len) = JSONValue::parse_with_len(contents)?;
-                    let (new_contents, whitespace) = trim_start(&contents[item_len..]);
-                    contents = new_contents;
-                    value_len += item_len + whitespace;
-                    if contents.is_empty() {
-                        return Err(JSONParsingError::EndOfStream);
-                    } else if contents.starts_with(',') {
-                        value_len += 1;
-                        contents = &contents[1..];
-                    } else if !contents.starts_with(']') {
-                        return Err(JSONParsingError::UnexpectedToken);
-                    }
-                }
-                (JSONValueType::Array, value_len)
-            }
-            Some('"') => {
-                let mut value_len = 1;
-                let mut is_escaped = false;
-                for chr in contents[1..].chars() {
-                    value_len += chr.len_utf8();
-                    if chr == '"' && !is_escaped {
-                        break;
-                    } else if chr == '\\' {
-                        is_escaped = !is_escaped;
-                    } else {
-                        is_escaped = false;
-                    }
-                }
-                (JSONValueType::String, value_len)
-            }
-            Some('0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | '-') => {
-                let mut value_len = 0;
-                for chr in contents.chars() {
-                    match chr {
-                        '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | '-' | 'e'
-                        | 'E' | '.' => {
-                            value_len += chr.len_utf8();
-                        }
-                        _ => {
-                            break;
-                        }
-                    }
-                }
-                (JSONValueType::Number, value_len)
-            }
-            Some('t') => {
-                if &contents[..4] != "true" {
-                    return Err(JSONParsingError::UnexpectedToken);
-                }
-                (JSONValueType::Bool, 4)
-            }
-            Some('f') => {
-                if &contents[..5] != "false" {
-                    return Err(JSONParsingError::UnexpectedToken);
-                }
-                (JSONValueType::Bool, 5)
-            }
-            Some('n') => {
-                if &contents[..4] != "null" {
-                    return Err(JSONParsingError::UnexpectedToken);
-                }
-                (JSONValueType::Null, 4)
-            }
-            _ => {
-                return Err(JSONParsingError::UnexpectedToken);
-            }
-        };
+    ///
+    /// `offset` is the position of `contents` in the original input, and is used to locate errors.
+    /// `depth` is the number of containers enclosing the value.
+    ///
+    /// If `strict` is false, this only scans far enough to find the end of the value, and so may
+    /// accept malformed values. If `strict` is true, the value must conform to RFC 8259.
+    fn parse_with_len(
+        contents: &'a str,
+        offset: usize,
+        depth: usize,
+        options: ParseOptions,
+        strict: bool,
+    ) -> Result<(JSONValue<'a>, usize), JSONParsingError> {
+        let whitespace_trimmed = whitespace_len(contents, offset, options, strict)?;
+        let contents = &contents[whitespace_trimmed..];
+        let offset = offset + whitespace_trimmed;
+        let value_len = scan::value_len(contents, offset, depth, options, strict)?;
         Ok((
             JSONValue {
                 contents: &contents[..value_len],
-                value_type,
+                offset,
+                depth,
+                options,
+                value_type: JSONValue::peek_value_type(contents, options),
             },
             whitespace_trimmed + value_len,
         ))
@@ -277,7 +272,7 @@ impl<'a> JSONValue<'a> {
     /// assert_eq!(value.is_null(), false);
     /// ```
     pub fn is_null(&self) -> bool {
-        self.contents.trim_end() == "null"
+        self.trim_end() == "null"
     }
 
     /// Reads the [`JSONValue`] as a boolean
@@ -286,67 +281,267 @@ impl<'a> JSONValue<'a> {
     ///
     /// ### Example
     /// ```
-    /// # use microjson::{JSONValue, JSONParsingError};
+    /// # use microjson::{JSONValue, JSONParsingErrorKind};
     /// let value = JSONValue::load("true");
     /// assert_eq!(value.read_boolean(), Ok(true));
     ///
     /// let value = JSONValue::load("f");
-    /// assert_eq!(value.read_boolean(), Err(JSONParsingError::CannotParseBoolean));
+    /// assert_eq!(value.read_boolean().unwrap_err().kind(), JSONParsingErrorKind::CannotParseBoolean);
     /// ```
     pub fn read_boolean(&self) -> Result<bool, JSONParsingError> {
         if self.value_type != JSONValueType::Bool {
-            return Err(JSONParsingError::CannotParseBoolean);
+            return Err(JSONParsingErrorKind::CannotParseBoolean.at(self.offset));
         }
-        let contents = self.contents.trim_end();
+        let contents = self.trim_end();
         if contents == "true" {
             Ok(true)
         } else if contents == "false" {
             Ok(false)
         } else {
-            Err(JSONParsingError::CannotParseBoolean)
+            Err(JSONParsingErrorKind::CannotParseBoolean.at(self.offset))
         }
     }
 
     /// Reads the [`JSONValue`] as an integer
     ///
-    /// If the type is not a [`JSONValueType::Number`], returns an `Err`.
+    /// If the type is not a [`JSONValueType::Number`], returns an `Err`. With
+    /// [`ParseOptions::with_integral_floats`], whole numbers such as `1.0` are read too, and other
+    /// numbers with a fractional part give [`JSONParsingErrorKind::NotAnInteger`].
     ///
     /// ### Example
     /// ```
-    /// # use microjson::{JSONValue, JSONParsingError};
+    /// # use microjson::{JSONValue, JSONParsingErrorKind};
     /// let value = JSONValue::load("-24");
     /// assert_eq!(value.read_integer(), Ok(-24));
     ///
     /// let value = JSONValue::load("5pi");
-    /// assert_eq!(value.read_integer(), Err(JSONParsingError::CannotParseInteger));
+    /// assert_eq!(value.read_integer().unwrap_err().kind(), JSONParsingErrorKind::CannotParseInteger);
     /// ```
     pub fn read_integer(&self) -> Result<isize, JSONParsingError> {
-        if self.value_type != JSONValueType::Number {
-            return Err(JSONParsingError::CannotParseInteger);
-        }
-        let contents = self.contents.trim_end();
-        PARSE_INT(contents).map_err(|_| JSONParsingError::CannotParseInteger)
+        self.read_number().map_err(|error| match error.kind() {
+            JSONParsingErrorKind::NotAnInteger if self.options.integral_floats() => error,
+            _ => JSONParsingErrorKind::CannotParseInteger.at(self.offset),
+        })
     }
 
     /// Reads the [`JSONValue`] as a float
     ///
-    /// If the type is not a [`JSONValueType::Number`], returns an `Err`.
+    /// If the type is not a [`JSONValueType::Number`], returns an `Err`. This needs the `float`
+    /// feature, which is enabled by default.
     ///
     /// ### Example
     /// ```
-    /// # use microjson::{JSONValue, JSONParsingError};
+    /// # use microjson::{JSONValue, JSONParsingErrorKind};
     /// let value = JSONValue::load("2.4");
     /// assert_eq!(value.read_float(), Ok(2.4));
     ///
     /// let value = JSONValue::load("5pi");
-    /// assert_eq!(value.read_float(), Err(JSONParsingError::CannotParseFloat));
+    /// assert_eq!(value.read_float().unwrap_err().kind(), JSONParsingErrorKind::CannotParseFloat);
     /// ```
+    #[cfg(feature = "float")]
     pub fn read_float(&self) -> Result<f32, JSONParsingError> {
+        self.read_number()
+            .map_err(|_| JSONParsingErrorKind::CannotParseFloat.at(self.offset))
+    }
+
+    /// Reads the [`JSONValue`] as a number of any primitive integer or floating point type
+    ///
+    /// If the type is not a [`JSONValueType::Number`], or the number is malformed, returns an
+    /// error of kind [`JSONParsingErrorKind::CannotParseNumber`]. Numbers that are too large or
+    /// small for `T` give [`JSONParsingErrorKind::NumberOutOfRange`], and numbers with a fractional
+    /// part or exponent give [`JSONParsingErrorKind::NotAnInteger`] if `T` is an integer type,
+    /// unless they are whole numbers and [`ParseOptions::with_integral_floats`] is set.
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::{JSONValue, JSONParsingErrorKind};
+    /// let value = JSONValue::load("1700000000000");
+    /// assert_eq!(value.read_number::<u64>(), Ok(1_700_000_000_000));
+    /// # #[cfg(feature = "float")]
+    /// assert_eq!(value.read_number::<f64>(), Ok(1.7e12));
+    ///
+    /// let value = JSONValue::load("-1");
+    /// assert_eq!(value.read_number::<u8>().unwrap_err().kind(), JSONParsingErrorKind::NumberOutOfRange);
+    ///
+    /// let value = JSONValue::load("2.5");
+    /// assert_eq!(value.read_number::<i32>().unwrap_err().kind(), JSONParsingErrorKind::NotAnInteger);
+    /// ```
+    pub fn read_number<T: JSONNumber>(&self) -> Result<T, JSONParsingError> {
+        let error = JSONParsingErrorKind::CannotParseNumber.at(self.offset);
+        if self.value_type != JSONValueType::Number {
+            return Err(error);
+        }
+        let parts =
+            NumberParts::split(self.trim_end(), self.options.json5_enabled()).ok_or(error)?;
+        T::from_parts(&parts, self.options).map_err(|kind| kind.at(self.offset))
+    }
+
+    /// Reads the [`JSONValue`] as an exact decimal number, without going through floating point
+    ///
+    /// If the type is not a [`JSONValueType::Number`], or the number is malformed, returns an
+    /// error of kind [`JSONParsingErrorKind::CannotParseNumber`]. Numbers whose digits do not fit
+    /// in a `u128` mantissa, or whose exponent does not fit in an `i32`, give
+    /// [`JSONParsingErrorKind::NumberOutOfRange`].
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::{Decimal, JSONValue};
+    /// let value = JSONValue::load("-12.345e1");
+    /// assert_eq!(
+    ///     value.read_decimal(),
+    ///     Ok(Decimal { negative: true, mantissa: 12345, exponent: -2 })
+    /// );
+    /// assert_eq!(value.read_decimal().unwrap().to_fixed(3), Ok(-123450));
+    /// ```
+    pub fn read_decimal(&self) -> Result<Decimal, JSONParsingError> {
+        let error = JSONParsingErrorKind::CannotParseNumber.at(self.offset);
+        if self.value_type != JSONValueType::Number {
+            return Err(error);
+        }
+        let parts =
+            NumberParts::split(self.trim_end(), self.options.json5_enabled()).ok_or(error)?;
+        parts.decimal().map_err(|kind| kind.at(self.offset))
+    }
+
+    /// The text of the [`JSONValue`] as a number, exactly as written
+    ///
+    /// Unlike the other accessors, this checks the number against the grammar of RFC 8259 (or
+    /// JSON5, if enabled), so the text can be passed on verbatim. If the type is not a
+    /// [`JSONValueType::Number`], returns an error of kind
+    /// [`JSONParsingErrorKind::CannotParseNumber`], and if the number is malformed, returns the
+    /// error [`JSONValue::verify`] would.
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::{JSONValue, JSONParsingErrorKind};
+    /// let value = JSONValue::load("123456789012345678901234567890 ");
+    /// assert_eq!(value.number_token(), Ok("123456789012345678901234567890"));
+    /// assert!(value.is_integer_literal());
+    /// assert!(!value.fits_u64());
+    ///
+    /// let value = JSONValue::load("0123");
+    /// assert_eq!(value.number_token().unwrap_err().kind(), JSONParsingErrorKind::UnexpectedToken);
+    /// ```
+    pub fn number_token(&self) -> Result<&'a str, JSONParsingError> {
         if self.value_type != JSONValueType::Number {
-            return Err(JSONParsingError::CannotParseFloat);
+            return Err(JSONParsingErrorKind::CannotParseNumber.at(self.offset));
+        }
+        let token = self.trim_end();
+        let len = scan::strict_number_len(token, self.offset, self.options.json5_enabled())?;
+        if len != token.len() {
+            return Err(JSONParsingErrorKind::UnexpectedToken.at(self.offset + len));
+        }
+        Ok(token)
+    }
+
+    /// The parts of the number, if it is well formed
+    fn number_parts(&self) -> Option<NumberParts<'a>> {
+        NumberParts::split(self.number_token().ok()?, self.options.json5_enabled())
+    }
+
+    /// Checks if the [`JSONValue`] is a number written as an integer, without a fractional part or
+    /// exponent
+    ///
+    /// JSON5 hexadecimal numbers are integers too. If the value is not a well formed number, returns
+    /// false.
+    pub fn is_integer_literal(&self) -> bool {
+        match self.number_parts().map(|parts| parts.magnitude) {
+            Some(Magnitude::Decimal { text, integer, .. }) => integer.len() == text.len(),
+            Some(Magnitude::Hexadecimal(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if the [`JSONValue`] is a number written with a decimal point
+    ///
+    /// If the value is not a well formed number, returns false.
+    pub fn has_fraction(&self) -> bool {
+        match self.number_parts().map(|parts| parts.magnitude) {
+            Some(Magnitude::Decimal { text, integer, .. }) => {
+                text[integer.len()..].starts_with('.')
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks if the [`JSONValue`] is a number written with an exponent
+    ///
+    /// If the value is not a well formed number, returns false.
+    pub fn has_exponent(&self) -> bool {
+        match self.number_parts().map(|parts| parts.magnitude) {
+            Some(Magnitude::Decimal { exponent, .. }) => !exponent.is_empty(),
+            _ => false,
         }
-        let contents = self.contents.trim_end();
-        PARSE_FLOAT(contents).map_err(|_| JSONParsingError::CannotParseFloat)
+    }
+
+    /// Checks if the [`JSONValue`] is a number written with a minus sign
+    ///
+    /// This includes `-0`. If the value is not a well formed number, returns false.
+    pub fn is_negative(&self) -> bool {
+        self.number_parts().is_some_and(|parts| parts.negative)
+    }
+
+    /// Checks if the [`JSONValue`] is a number that can be read as an `i64`
+    ///
+    /// If the value is not a well formed number, returns false.
+    pub fn fits_i64(&self) -> bool {
+        self.number_fits::<i64>()
+    }
+
+    /// Checks if the [`JSONValue`] is a number that can be read as a `u64`
+    ///
+    /// If the value is not a well formed number, returns false.
+    pub fn fits_u64(&self) -> bool {
+        self.number_fits::<u64>()
+    }
+
+    fn number_fits<T: JSONNumber>(&self) -> bool {
+        self.number_parts()
+            .is_some_and(|parts| T::from_parts(&parts, self.options).is_ok())
+    }
+
+    /// Reads the [`JSONValue`] as a fixed-point number with `scale` decimal places, that is the
+    /// number multiplied by `10^scale`
+    ///
+    /// No floating point arithmetic is used. Digits beyond the last decimal place are rounded to
+    /// the nearest, with ties going to the even number. Use [`JSONValue::read_decimal`] and
+    /// [`Decimal::to_fixed`] instead to reject them. Errors are as for
+    /// [`JSONValue::read_decimal`], and numbers that do not fit in an `i64` once scaled give
+    /// [`JSONParsingErrorKind::NumberOutOfRange`].
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::JSONValue;
+    /// let value = JSONValue::load("12.345e-1");
+    /// assert_eq!(value.read_scaled(4), Ok(12345));
+    /// assert_eq!(value.read_scaled(2), Ok(123));
+    /// assert_eq!(value.read_scaled(0), Ok(1));
+    /// ```
+    pub fn read_scaled(&self, scale: u32) -> Result<i64, JSONParsingError> {
+        self.read_decimal()?
+            .to_scaled(scale)
+            .map_err(|kind| kind.at(self.offset))
+    }
+
+    /// Reads the [`JSONValue`] as a binary fixed-point number with `FRAC_BITS` fractional bits,
+    /// that is the number multiplied by `2^FRAC_BITS`
+    ///
+    /// No floating point arithmetic is used. The result is rounded to the nearest, with ties going
+    /// to the even number. Errors are as for [`JSONValue::read_decimal`], and numbers that do not
+    /// fit in an `i64` once scaled give [`JSONParsingErrorKind::NumberOutOfRange`], as do all
+    /// non-zero numbers if `FRAC_BITS` is 64 or more.
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::JSONValue;
+    /// // As a Q16.16 number
+    /// let value = JSONValue::load("-1.25");
+    /// assert_eq!(value.read_fixed::<16>(), Ok(-0x1_4000));
+    /// ```
+    pub fn read_fixed<const FRAC_BITS: u32>(&self) -> Result<i64, JSONParsingError> {
+        self.read_decimal()?
+            .to_binary_fixed(FRAC_BITS)
+            .map_err(|kind| kind.at(self.offset))
     }
 
     /// Read the [`JSONValue`] as a string
@@ -361,11 +556,263 @@ impl<'a> JSONValue<'a> {
     /// assert_eq!(value.read_string(), Ok("this is a string"));
     /// ```
     pub fn read_string(&self) -> Result<&'a str, JSONParsingError> {
-        let (_, length) = JSONValue::parse_with_len(self.contents)?;
         if self.value_type != JSONValueType::String {
-            return Err(JSONParsingError::CannotParseString);
+            return Err(JSONParsingErrorKind::CannotParseString.at(self.offset));
+        }
+        let (value, _) =
+            JSONValue::parse_with_len(self.contents, self.offset, self.depth, self.options, false)?;
+        if value.value_type != JSONValueType::String {
+            return Err(JSONParsingErrorKind::CannotParseString.at(self.offset));
+        }
+        Ok(&value.contents[1..value.contents.len() - 1])
+    }
+
+    /// Read the [`JSONValue`] as a string, decoding any escape sequences into `buffer`
+    ///
+    /// If the string has no escape sequences, it is returned directly without using the buffer.
+    /// Otherwise the decoded string is written to the start of the buffer, and that part of the
+    /// buffer is returned. If it does not fit, returns an error of kind
+    /// [`JSONParsingErrorKind::BufferTooSmall`] with the length needed. Escape sequences are
+    /// handled as for [`JSONValue::iter_string`].
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::{JSONValue, JSONParsingErrorKind};
+    /// let value = JSONValue::load(r#" "caf\u00e9 \"au lait\"" "#);
+    /// let mut buffer = [0; 32];
+    /// assert_eq!(value.read_string_into(&mut buffer), Ok("café \"au lait\""));
+    ///
+    /// let mut buffer = [0; 8];
+    /// assert_eq!(
+    ///     value.read_string_into(&mut buffer).unwrap_err().kind(),
+    ///     JSONParsingErrorKind::BufferTooSmall(15)
+    /// );
+    /// ```
+    pub fn read_string_into<'b>(&self, buffer: &'b mut [u8]) -> Result<&'b str, JSONParsingError>
+    where
+        'a: 'b,
+    {
+        let raw = self.read_string()?;
+        if !raw.contains('\\') {
+            return Ok(raw);
+        }
+        // Keep decoding after running out of room, to find how much is needed
+        let mut len = 0;
+        for chr in self.iter_string()? {
+            let chr = chr?;
+            if let Some(space) = buffer.get_mut(len..len + chr.len_utf8()) {
+                chr.encode_utf8(space);
+            }
+            len += chr.len_utf8();
+        }
+        if len > buffer.len() {
+            return Err(JSONParsingErrorKind::BufferTooSmall(len).at(self.offset));
+        }
+        // Only whole characters were written
+        core::str::from_utf8(&buffer[..len])
+            .map_err(|_| JSONParsingErrorKind::CannotParseString.at(self.offset))
+    }
+
+    /// Read the [`JSONValue`] as a string, decoding any escape sequences
+    ///
+    /// The string is borrowed from the input if it has no escape sequences, and otherwise decoded
+    /// into a newly allocated [`String`].  Requires the `alloc` feature.
+    ///
+    /// ## Example
+    /// ```
+    /// # use microjson::JSONValue;
+    /// # use std::borrow::Cow;
+    /// let value = JSONValue::load(r#" "no escapes" "#);
+    /// assert_eq!(value.read_string_cow(), Ok(Cow::Borrowed("no escapes")));
+    ///
+    /// let value = JSONValue::load(r#" "caf\u00e9" "#);
+    /// assert_eq!(value.read_string_cow(), Ok(Cow::Owned("café".into())));
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn read_string_cow(&self) -> Result<Cow<'a, str>, JSONParsingError> {
+        let raw = self.read_string()?;
+        if !raw.contains('\\') {
+            return Ok(Cow::Borrowed(raw));
+        }
+        self.iter_string()?
+            .collect::<Result<String, _>>()
+            .map(Cow::Owned)
+    }
+
+    /// Compare the [`JSONValue`] as a string with `other`, decoding any escape sequences
+    ///
+    /// Unlike comparing the result of [`JSONValue::read_string`], this finds that `"caf\u00e9"`
+    /// is equal to `"café"`.  It does not allocate, and stops decoding at the first difference, so
+    /// a malformed escape sequence after it is not reported.
+    ///
+    /// ## Example
+    /// ```
+    /// # use microjson::JSONValue;
+    /// let value = JSONValue::load(r#" "caf\u00e9" "#);
+    /// assert_eq!(value.string_eq("café"), Ok(true));
+    /// assert_eq!(value.string_eq("cafe"), Ok(false));
+    /// ```
+    pub fn string_eq(&self, other: &str) -> Result<bool, JSONParsingError> {
+        self.chars_eq(other.chars())
+    }
+
+    /// Compare the [`JSONValue`] as a string with the characters of `other`, decoding any escape
+    /// sequences, as for [`JSONValue::string_eq`]
+    fn chars_eq(&self, mut other: impl Iterator<Item = char>) -> Result<bool, JSONParsingError> {
+        let raw = self.read_string()?;
+        if !raw.contains('\\') {
+            return Ok(raw.chars().eq(other));
+        }
+        for chr in self.iter_string()? {
+            if Some(chr?) != other.next() {
+                return Ok(false);
+            }
+        }
+        Ok(other.next().is_none())
+    }
+
+    /// The contents of this value without any trailing whitespace, or comments if enabled
+    fn trim_end(&self) -> &'a str {
+        if self.options.comments_enabled() {
+            // Numbers, booleans and null never contain a slash, so anything after one must be a
+            // comment if this is a well formed scalar
+            if let Some(start) = self.contents.find('/') {
+                let rest = &self.contents[start..];
+                if whitespace_len(rest, 0, self.options, false) == Ok(rest.len()) {
+                    return self.contents[..start].trim_end();
+                }
+            }
+        }
+        self.contents.trim_end()
+    }
+
+    /// The contents of this value after its opening bracket or quote
+    fn inner(&self) -> &'a str {
+        // The value type is public, so we can't assume that it matches the contents
+        self.contents.get(1..).unwrap_or_default()
+    }
+
+    /// The positions of the items of this array, or entries of this object, found by matching
+    /// brackets and quotes only
+    fn item_starts(&self) -> ItemStarts<'a> {
+        ItemStarts::new(self.inner(), self.offset + 1, self.options)
+    }
+
+    /// The number of items in this array, or entries in this object
+    ///
+    /// The items are counted by matching brackets and quotes, without checking or decoding them,
+    /// which is much faster than iterating.  For a malformed payload, the count may differ from
+    /// the number of items that iteration finds.
+    ///
+    /// If the value is neither an array nor an object, returns an error of kind
+    /// [`JSONParsingErrorKind::CannotParseArray`].
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::JSONValue;
+    /// let value = JSONValue::load(r#" [1, [2, 3], {"a": ",]"}] "#);
+    /// assert_eq!(value.len(), Ok(3));
+    /// ```
+    pub fn len(&self) -> Result<usize, JSONParsingError> {
+        if !matches!(
+            self.value_type,
+            JSONValueType::Array | JSONValueType::Object
+        ) {
+            return Err(JSONParsingErrorKind::CannotParseArray.at(self.offset));
         }
-        Ok(&self.contents[1..length - 1])
+        self.item_starts()
+            .try_fold(0, |count, start| start.map(|_| count + 1))
+    }
+
+    /// Checks if this array or object has no items
+    ///
+    /// If the value is neither an array nor an object, returns an error of kind
+    /// [`JSONParsingErrorKind::CannotParseArray`].
+    pub fn is_empty(&self) -> Result<bool, JSONParsingError> {
+        if !matches!(
+            self.value_type,
+            JSONValueType::Array | JSONValueType::Object
+        ) {
+            return Err(JSONParsingErrorKind::CannotParseArray.at(self.offset));
+        }
+        Ok(self.item_starts().next().transpose()?.is_none())
+    }
+
+    /// Reads the item at `index` in this array
+    ///
+    /// Unlike [`Iterator::nth`] on [`JSONValue::iter_array`], this skips over the items before
+    /// `index` by matching brackets and quotes only, without checking or decoding them.
+    ///
+    /// If the value is not an [`JSONValueType::Array`], returns an error, and if the array is too
+    /// short, returns an error of kind [`JSONParsingErrorKind::IndexOutOfRange`].
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::JSONValue;
+    /// let value = JSONValue::load(r#" [{"a": [1, 2]}, "]", 3] "#);
+    /// assert_eq!(value.get_index(2).unwrap().read_integer(), Ok(3));
+    /// ```
+    pub fn get_index(&self, index: usize) -> Result<JSONValue<'a>, JSONParsingError> {
+        if self.value_type != JSONValueType::Array {
+            return Err(JSONParsingErrorKind::CannotParseArray.at(self.offset));
+        }
+        let start = self
+            .item_starts()
+            .nth(index)
+            .ok_or_else(|| JSONParsingErrorKind::IndexOutOfRange.at(self.offset))??;
+        self.item_at(start)
+    }
+
+    /// Read the item of this array at byte `start` after its opening bracket
+    fn item_at(&self, start: usize) -> Result<JSONValue<'a>, JSONParsingError> {
+        let (value, _) = JSONValue::parse_with_len(
+            self.inner().get(start..).unwrap_or_default(),
+            self.offset + 1 + start,
+            self.depth + 1,
+            self.options,
+            false,
+        )?;
+        Ok(value)
+    }
+
+    /// Builds an index of this array or object, using `buffer` to store the offsets of its items
+    ///
+    /// Afterwards, the items of an array can be read by position, and the values of an object by
+    /// key, without reading through the items before them.  Building the index skips over items
+    /// as [`JSONValue::len`] does, and checks only the keys of objects.  The buffer needs a place
+    /// for each item, or returns an error of kind [`JSONParsingErrorKind::BufferTooSmall`] with
+    /// the number of places needed.
+    ///
+    /// If the value is neither an array nor an object, returns an error of kind
+    /// [`JSONParsingErrorKind::CannotParseArray`].
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::{JSONValue, JSONParsingError};
+    /// let value = JSONValue::load(r#" [{"id": 1, "name": "Ana"}, {"name": "Bo", "id": 2}] "#);
+    /// let mut buffer = [0; 8];
+    /// let people = value.index_into(&mut buffer)?;
+    /// assert_eq!(people.len(), 2);
+    ///
+    /// let mut buffer = [0; 2];
+    /// let bo = people.get_index(1)?.index_into(&mut buffer)?;
+    /// assert_eq!(bo.get_key_value("id")?.read_integer(), Ok(2));
+    /// # Ok::<(), JSONParsingError>(())
+    /// ```
+    pub fn index_into<'b>(
+        &self,
+        buffer: &'b mut [u32],
+    ) -> Result<JSONIndex<'a, &'b [u32]>, JSONParsingError> {
+        JSONIndex::new_in(*self, buffer)
+    }
+
+    /// Builds an index of this array or object, as [`JSONValue::index_into`] does, but storing
+    /// the offsets of its items in a `Vec`
+    ///
+    /// Requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    pub fn index(&self) -> Result<JSONIndex<'a, alloc::vec::Vec<u32>>, JSONParsingError> {
+        JSONIndex::new(*self)
     }
 
     /// Constructs an iterator over this array value
@@ -373,10 +820,13 @@ impl<'a> JSONValue<'a> {
     /// If the value is not an [`JSONValueType::Array`], returns an error.
     pub fn iter_array(&self) -> Result<JSONArrayIterator<'a>, JSONParsingError> {
         if self.value_type != JSONValueType::Array {
-            return Err(JSONParsingError::CannotParseArray);
+            return Err(JSONParsingErrorKind::CannotParseArray.at(self.offset));
         }
         Ok(JSONArrayIterator {
-            contents: &self.contents[1..],
+            contents: self.inner(),
+            offset: self.offset + 1,
+            depth: self.depth + 1,
+            options: self.options,
         })
     }
 
@@ -384,7 +834,8 @@ impl<'a> JSONValue<'a> {
     ///
     /// If the value is not an [`JSONValueType::String`], returns an error.
     ///
-    /// The iterator returns [`Result<char, JSONParsingError>`]s and handles escape sequences.
+    /// The iterator returns [`Result<char, JSONParsingError>`]s and handles escape sequences,
+    /// including characters outside the Basic Multilingual Plane escaped as UTF-16 surrogate pairs.
     /// You can convert this into a `Result<String, _>` using `collect`.
     ///
     /// ### Example
@@ -392,16 +843,26 @@ impl<'a> JSONValue<'a> {
     /// # use microjson::JSONValue;
     /// let value = JSONValue::load(r#" "\u27FC This is a string with unicode \u27FB""#);
     /// let string : Result<String, _> = value.iter_string().unwrap().collect::<Result<String, _>>();
-    /// assert_eq!(string.unwrap(), "⟼ This is a string with unicode ⟻")
+    /// assert_eq!(string.unwrap(), "⟼ This is a string with unicode ⟻");
+    ///
+    /// let value = JSONValue::load(r#" "\ud83d\ude00" "#);
+    /// let string : Result<String, _> = value.iter_string().unwrap().collect::<Result<String, _>>();
+    /// assert_eq!(string.unwrap(), "😀");
     /// ```
     pub fn iter_string(&self) -> Result<EscapedStringIterator<'a>, JSONParsingError> {
         if self.value_type != JSONValueType::String {
-            return Err(JSONParsingError::CannotParseString);
+            return Err(JSONParsingErrorKind::CannotParseString.at(self.offset));
         }
-        Ok(EscapedStringIterator {
-            contents: self.contents[1..].chars(),
-            done: false,
-        })
+        let quote = match self.contents.chars().next() {
+            Some('\'') => '\'',
+            _ => '"',
+        };
+        Ok(EscapedStringIterator::new(
+            self.inner(),
+            self.offset + 1,
+            quote,
+            self.options,
+        ))
     }
 
     /// Constructs an iterator over this object
@@ -409,10 +870,13 @@ impl<'a> JSONValue<'a> {
     /// If the value is not an [`JSONValueType::Object`], returns an error.
     pub fn iter_object(&self) -> Result<JSONObjectIterator<'a>, JSONParsingError> {
         if self.value_type != JSONValueType::Object {
-            return Err(JSONParsingError::CannotParseObject);
+            return Err(JSONParsingErrorKind::CannotParseObject.at(self.offset));
         }
         Ok(JSONObjectIterator {
-            contents: &self.contents[1..],
+            contents: self.inner(),
+            offset: self.offset + 1,
+            depth: self.depth + 1,
+            options: self.options,
         })
     }
 
@@ -422,62 +886,386 @@ impl<'a> JSONValue<'a> {
     /// object to find the desired one. If parsing the entire object in an arbitrary order, then,
     /// prefer using [`JSONValue::iter_object`].
     ///
-    /// Will return `Err(JSONParsingError::KeyNotFound)` if the key is not present.
-    pub fn get_key_value(&self, key: &str) -> Result<JSONValue, JSONParsingError> {
-        self.iter_object()?
-            .find(|item| matches!(item, Ok((k, _)) if k == &key))
-            .map(|item| item.unwrap().1)
-            .ok_or(JSONParsingError::KeyNotFound)
+    /// Keys are compared after decoding their escape sequences, as with [`JSONValue::string_eq`].
+    ///
+    /// Will return an error of kind [`JSONParsingErrorKind::KeyNotFound`] if the key is not
+    /// present, or the error encountered if the object is malformed before the key is found.
+    pub fn get_key_value(&self, key: &str) -> Result<JSONValue<'a>, JSONParsingError> {
+        self.find_key(key.chars())?
+            .ok_or_else(|| JSONParsingErrorKind::KeyNotFound.at(self.offset))
     }
-}
-
-/// An iterator through a JSON object
-///
-/// Usually constructed with [`JSONValue::iter_object`].
-///
-/// The iterator items are `Result<(key, value), JSONParsingError>`, but the key is not escaped
-pub struct JSONObjectIterator<'a> {
-    contents: &'a str,
-}
 
-impl<'a> Iterator for JSONObjectIterator<'a> {
-    type Item = Result<(&'a str, JSONValue<'a>), JSONParsingError>;
+    /// Searches this object for several keys at once, and returns their values
+    ///
+    /// Unlike calling [`JSONValue::get_key_value`] for each key, this reads through the object
+    /// only once.  Keys are compared after decoding their escape sequences, and the value of a
+    /// key that is not present is `None`.
+    ///
+    /// Will return an error of kind [`JSONParsingErrorKind::DuplicateKey`], at the second value,
+    /// if one of the keys appears more than once, or the error encountered if the object is
+    /// malformed.
+    ///
+    /// ## Example
+    /// ```
+    /// # use microjson::{JSONValue, JSONParsingError};
+    /// let value = JSONValue::load(r#" {"id": 7, "name": "Ginger", "tags": []} "#);
+    /// let [id, name, email] = value.get_key_values(["id", "name", "email"])?;
+    /// assert_eq!(id.unwrap().read_integer(), Ok(7));
+    /// assert_eq!(name.unwrap().read_string(), Ok("Ginger"));
+    /// assert!(email.is_none());
+    /// # Ok::<(), JSONParsingError>(())
+    /// ```
+    pub fn get_key_values<const N: usize>(
+        &self,
+        keys: [&str; N],
+    ) -> Result<[Option<JSONValue<'a>>; N], JSONParsingError> {
+        let mut values = [None; N];
+        self.get_key_values_into(&keys, &mut values)?;
+        Ok(values)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.contents = self.contents.trim_start();
-        if self.contents.is_empty() {
-            None
-        } else {
-            if !self.contents.starts_with('\"') {
-                self.contents = &self.contents[..0];
-                return None;
-            }
-            // We expect this to be a string value for the key
-            match JSONValue::parse_with_len(self.contents) {
-                Ok((_, key_len)) => {
-                    let this_key = &self.contents[1..key_len - 1];
-                    self.contents = &self.contents[key_len..].trim_start()[1..];
-
-                    match JSONValue::parse_with_len(self.contents) {
-                        Ok((this_value, value_len)) => {
-                            self.contents = &self.contents[value_len..].trim_start();
-                            if !self.contents.is_empty() {
-                                self.contents = &self.contents[1..];
-                            }
-                            Some(Ok((this_key, this_value)))
-                        }
-                        Err(e) => {
-                            self.contents = &self.contents[..0];
-                            Some(Err(e))
-                        }
+    /// Searches this object for several keys at once, storing the value of `keys[i]` in
+    /// `values[i]`
+    ///
+    /// This is [`JSONValue::get_key_values`] for when the number of keys is not known at compile
+    /// time.  Values of keys that are not present are set to `None`, and keys without a place in
+    /// `values` are ignored.
+    pub fn get_key_values_into(
+        &self,
+        keys: &[&str],
+        values: &mut [Option<JSONValue<'a>>],
+    ) -> Result<(), JSONParsingError> {
+        values.fill(None);
+        let mut items = self.iter_object()?;
+        while let Some(item) = items.next_entry() {
+            let (k, value) = item?;
+            for (key, slot) in keys.iter().zip(values.iter_mut()) {
+                if k.matches(key.chars())? {
+                    if slot.is_some() {
+                        return Err(JSONParsingErrorKind::DuplicateKey.at(value.offset));
                     }
-                }
-                Err(e) => {
-                    self.contents = &self.contents[..0];
-                    Some(Err(e))
+                    *slot = Some(value);
+                    break;
                 }
             }
         }
+        Ok(())
+    }
+
+    /// Search this object for the value of the key made of the characters of `key`
+    fn find_key(
+        &self,
+        key: impl Iterator<Item = char> + Clone,
+    ) -> Result<Option<JSONValue<'a>>, JSONParsingError> {
+        let mut items = self.iter_object()?;
+        while let Some(item) = items.next_entry() {
+            let (k, value) = item?;
+            if k.matches(key.clone())? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Look up a value by its [JSON Pointer](https://www.rfc-editor.org/rfc/rfc6901)
+    ///
+    /// Each segment of the pointer, separated by `/`, is either an object key or an array index.
+    /// As in RFC 6901, `~1` stands for a `/` in a key and `~0` for a `~`.  The empty pointer
+    /// refers to this value itself.
+    ///
+    /// Keys are compared after decoding their escape sequences, as with
+    /// [`JSONValue::get_key_value`].  Like that function, this searches linearly through each
+    /// array and object on the path.
+    ///
+    /// Will return an error of kind [`JSONParsingErrorKind::PathNotFound`] holding the index of
+    /// the first segment that could not be followed, counting from zero, or the error encountered
+    /// if the payload is malformed along the way.  A pointer that does not start with `/`, or
+    /// has a `~` that is not followed by `0` or `1`, cannot be followed either.
+    ///
+    /// ## Example
+    /// ```
+    /// # use microjson::{JSONValue, JSONParsingErrorKind};
+    /// let value = JSONValue::load(r#" {"friends": [{"name": "Ana"}, {"name": "Bo"}], "a/b": 1} "#);
+    /// assert_eq!(value.pointer("/friends/1/name")?.read_string(), Ok("Bo"));
+    /// assert_eq!(value.pointer("/a~1b")?.read_integer(), Ok(1));
+    /// assert_eq!(
+    ///     value.pointer("/friends/2/name").unwrap_err().kind(),
+    ///     JSONParsingErrorKind::PathNotFound(1)
+    /// );
+    /// # Ok::<(), microjson::JSONParsingError>(())
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Result<JSONValue<'a>, JSONParsingError> {
+        if pointer.is_empty() {
+            return Ok(*self);
+        }
+        let segments = pointer
+            .strip_prefix('/')
+            .ok_or_else(|| JSONParsingErrorKind::PathNotFound(0).at(self.offset))?;
+        let mut value = *self;
+        for (index, segment) in segments.split('/').enumerate() {
+            let not_found = JSONParsingErrorKind::PathNotFound(index).at(value.offset);
+            let token = PointerToken::new(segment).ok_or(not_found)?;
+            let next = match value.value_type {
+                JSONValueType::Object => value.find_key(token)?,
+                JSONValueType::Array => token
+                    .index()
+                    .and_then(|index| value.iter_array().ok()?.nth(index)),
+                _ => None,
+            };
+            value = next.ok_or(not_found)?;
+        }
+        Ok(value)
+    }
+
+    /// Select values with a [JSONPath](https://www.rfc-editor.org/rfc/rfc9535) query
+    ///
+    /// The query starts from this value, `$`, and supports these segments:
+    ///
+    /// | Segment                              | Selects                                          |
+    /// |:-------------------------------------|:-------------------------------------------------|
+    /// | `.name`, `['name']`                  | The value of a key in an object                  |
+    /// | `.*`, `[*]`                          | Every value in an array or object                |
+    /// | `[2]`, `[-1]`                        | An item in an array, counting back from the end if negative |
+    /// | `[start:end:step]`                   | A slice of an array, where each part is optional and the step is positive |
+    /// | `[?(@.path)]`                        | Each value in an array or object that has a value at the path |
+    /// | `[?(@.path == "x")]`                 | Each value in an array or object whose value at the path compares with a literal |
+    /// | `..name`, `..*`, `..[...]`           | As above, but from every value nested within, rather than just the children |
+    ///
+    /// The path in a filter is a sequence of keys and array indices, such as `@.a['b'][0]`, or
+    /// just `@`.  It is compared using one of `==`, `!=`, `<`, `<=`, `>` and `>=` with a string,
+    /// number, `true`, `false` or `null`.  Numbers are compared by their exact values, strings
+    /// after decoding any escape sequences in the payload (but not the query), and only numbers
+    /// can be ordered.  A value of another type than the literal does not match, but a number
+    /// that cannot be read, or a malformed object on the path, is reported as an error.
+    ///
+    /// Will return an error of kind [`JSONParsingErrorKind::InvalidPath`] holding the position in
+    /// the query at which it is malformed.  Otherwise, the [`JSONPathIterator`] yields each
+    /// selected value in turn, or the error encountered if the payload is malformed.
+    ///
+    /// The query does not allocate, so the number of arrays and objects that it can be part way
+    /// through at once is limited to [`JSONPathIterator::MAX_DEPTH`].  Going deeper is reported
+    /// as [`JSONParsingErrorKind::DepthLimitExceeded`], and the search continues without it.  A
+    /// value or query longer than 4 GiB gives an error of kind
+    /// [`JSONParsingErrorKind::ValueTooLong`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use microjson::{JSONValue, JSONParsingError};
+    /// let value = JSONValue::load(
+    ///     r#" {"devices": [
+    ///         {"sensors": [{"type": "temp", "value": 21}, {"type": "humidity", "value": 40}]},
+    ///         {"sensors": [{"type": "temp", "value": 19}]}
+    ///     ]} "#,
+    /// );
+    /// let temperatures = value
+    ///     .query(r#"$.devices[*].sensors[?(@.type == "temp")].value"#)?
+    ///     .map(|value| value?.read_integer())
+    ///     .collect::<Result<Vec<_>, JSONParsingError>>()?;
+    /// assert_eq!(temperatures, [21, 19]);
+    ///
+    /// let high = value.query("$..[?(@.value > 20)].type")?.count();
+    /// assert_eq!(high, 2);
+    /// # Ok::<(), JSONParsingError>(())
+    /// ```
+    pub fn query<'p>(&self, path: &'p str) -> Result<JSONPathIterator<'a, 'p>, JSONParsingError> {
+        JSONPathIterator::new(*self, path)
+    }
+}
+
+/// An object key as it appears in the input
+#[derive(Copy, Clone)]
+enum ObjectKey<'a> {
+    /// A string, which may contain escape sequences
+    Quoted(JSONValue<'a>),
+    /// A JSON5 identifier
+    Unquoted(&'a str),
+}
+
+impl<'a> ObjectKey<'a> {
+    /// The key without its quotes or any escape sequences decoded
+    fn raw(self) -> &'a str {
+        match self {
+            ObjectKey::Quoted(value) => &value.contents[1..value.contents.len() - 1],
+            ObjectKey::Unquoted(key) => key,
+        }
+    }
+
+    /// The key with any escape sequences decoded
+    #[cfg(feature = "alloc")]
+    fn decode(self) -> Result<String, JSONParsingError> {
+        match self {
+            ObjectKey::Quoted(value) => value.read_string_cow().map(Cow::into_owned),
+            ObjectKey::Unquoted(key) => Ok(key.into()),
+        }
+    }
+
+    /// Whether the key, once decoded, is `key`
+    fn matches(self, key: impl Iterator<Item = char>) -> Result<bool, JSONParsingError> {
+        match self {
+            ObjectKey::Quoted(value) => value.chars_eq(key),
+            ObjectKey::Unquoted(raw) => Ok(raw.chars().eq(key)),
+        }
+    }
+}
+
+/// An iterator through a JSON object
+///
+/// Usually constructed with [`JSONValue::iter_object`].
+///
+/// The iterator items are `Result<(key, value), JSONParsingError>`, but the key is not escaped
+pub struct JSONObjectIterator<'a> {
+    contents: &'a str,
+    /// Byte offset of `contents` in the original input
+    offset: usize,
+    /// The depth of the values in the object
+    depth: usize,
+    options: ParseOptions,
+}
+
+impl<'a> JSONObjectIterator<'a> {
+    fn advance(&mut self, len: usize) {
+        self.contents = &self.contents[len..];
+        self.offset += len;
+    }
+
+    fn trim_start(&mut self) -> Result<(), JSONParsingError> {
+        let whitespace = whitespace_len(self.contents, self.offset, self.options, false)?;
+        self.advance(whitespace);
+        Ok(())
+    }
+
+    fn finish(&mut self) {
+        self.advance(self.contents.len());
+    }
+
+    fn parse_with_len(&self) -> Result<(JSONValue<'a>, usize), JSONParsingError> {
+        JSONValue::parse_with_len(self.contents, self.offset, self.depth, self.options, false)
+    }
+
+    /// Read the key at the start of the contents, returning it along with its length, or `None`
+    /// if there is no key
+    fn read_key(&self) -> Result<Option<(ObjectKey<'a>, usize)>, JSONParsingError> {
+        let json5 = self.options.json5_enabled();
+        if json5 {
+            let key_len = scan::identifier_len(self.contents);
+            if key_len > 0 {
+                return Ok(Some((
+                    ObjectKey::Unquoted(&self.contents[..key_len]),
+                    key_len,
+                )));
+            }
+        }
+        if !(self.contents.starts_with('"') || (json5 && self.contents.starts_with('\''))) {
+            return Ok(None);
+        }
+        // We expect this to be a string value for the key
+        let (key, key_len) = self.parse_with_len()?;
+        Ok(Some((ObjectKey::Quoted(key), key_len)))
+    }
+
+    /// Read the next key and value, leaving the contents after them and any following comma
+    fn read_entry(&mut self) -> Result<Option<(ObjectKey<'a>, JSONValue<'a>)>, JSONParsingError> {
+        self.trim_start()?;
+        let (key, key_len) = match self.read_key()? {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        self.advance(key_len);
+        self.trim_start()?;
+        if !self.contents.starts_with(':') {
+            let error = if self.contents.is_empty() {
+                JSONParsingErrorKind::EndOfStream
+            } else {
+                JSONParsingErrorKind::UnexpectedToken
+            };
+            return Err(error.at(self.offset));
+        }
+        self.advance(1);
+
+        let (value, value_len) = self.parse_with_len()?;
+        self.advance(value_len);
+        self.trim_start()?;
+        if self.contents.starts_with(',') {
+            self.advance(1);
+        } else if self.contents.starts_with('}') {
+            self.finish();
+        }
+        Ok(Some((key, value)))
+    }
+
+    /// Convert this into an iterator whose keys have their escape sequences decoded
+    ///
+    /// Requires the `alloc` feature.
+    ///
+    /// ## Example
+    /// ```
+    /// # use microjson::JSONValue;
+    /// let value = JSONValue::load(r#" {"caf\u00e9": 1} "#);
+    /// let (key, _) = value.iter_object().unwrap().decode_keys().next().unwrap().unwrap();
+    /// assert_eq!(key, "café");
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn decode_keys(self) -> DecodedKeysIterator<'a> {
+        DecodedKeysIterator { entries: self }
+    }
+
+    /// Read the next entry, decoding its key
+    ///
+    /// This is [`Iterator::next`], but with the key decoded as by [`JSONValue::read_string_cow`].
+    /// Requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    pub fn next_decoded(&mut self) -> Option<Result<(String, JSONValue<'a>), JSONParsingError>> {
+        self.next_entry()
+            .map(|entry| entry.and_then(|(key, value)| Ok((key.decode()?, value))))
+    }
+
+    /// The next entry, with its key as it appears in the input
+    fn next_entry(&mut self) -> Option<Result<(ObjectKey<'a>, JSONValue<'a>), JSONParsingError>> {
+        if self.contents.is_empty() {
+            return None;
+        }
+        match self.read_entry() {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => {
+                self.finish();
+                None
+            }
+            Err(e) => {
+                self.finish();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for JSONObjectIterator<'a> {
+    type Item = Result<(&'a str, JSONValue<'a>), JSONParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry()
+            .map(|entry| entry.map(|(key, value)| (key.raw(), value)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // An entry takes at least three bytes, and only an error can follow a shorter remainder
+        (0, Some(self.contents.len().div_ceil(3)))
+    }
+}
+
+/// An iterator through a JSON object, whose keys have their escape sequences decoded
+///
+/// Constructed with [`JSONObjectIterator::decode_keys`].
+#[cfg(feature = "alloc")]
+pub struct DecodedKeysIterator<'a> {
+    entries: JSONObjectIterator<'a>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> Iterator for DecodedKeysIterator<'a> {
+    type Item = Result<(String, JSONValue<'a>), JSONParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next_decoded()
     }
 }
 
@@ -486,15 +1274,40 @@ impl<'a> Iterator for JSONObjectIterator<'a> {
 /// Usually constructed with [`JSONValue::iter_array`].
 pub struct JSONArrayIterator<'a> {
     contents: &'a str,
+    /// Byte offset of `contents` in the original input
+    offset: usize,
+    /// The depth of the values in the array
+    depth: usize,
+    options: ParseOptions,
 }
 
 impl<'a> Iterator for JSONArrayIterator<'a> {
     type Item = JSONValue<'a>;
 
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Every item takes at least one byte
+        (0, Some(self.contents.len()))
+    }
+
     fn next(&mut self) -> Option<Self::Item> {
-        match JSONValue::parse_with_len(self.contents) {
+        match JSONValue::parse_with_len(self.contents, self.offset, self.depth, self.options, false)
+        {
             Ok((value, value_len)) => {
-                self.contents = &self.contents[value_len..].trim_start()[1..];
+                let contents = &self.contents[value_len..];
+                // An unterminated comment ends the array
+                let whitespace =
+                    whitespace_len(contents, self.offset + value_len, self.options, false)
+                        .unwrap_or(contents.len());
+                let contents = &contents[whitespace..];
+                self.offset += value_len + whitespace;
+                self.contents = contents;
+                if let Some(contents) = contents.strip_prefix(',') {
+                    self.contents = contents;
+                    self.offset += 1;
+                } else if contents.starts_with(']') {
+                    self.offset += contents.len();
+                    self.contents = "";
+                }
                 Some(value)
             }
             _ => None,
@@ -507,65 +1320,145 @@ impl<'a> Iterator for JSONArrayIterator<'a> {
 /// See [`JSONValue::iter_string`] for further documentation.
 pub struct EscapedStringIterator<'a> {
     contents: core::str::Chars<'a>,
+    /// Byte offset of the end of `contents` in the original input
+    end_offset: usize,
+    /// The quote that ends the string, which may be `'` in JSON5
+    quote: char,
+    json5: bool,
     done: bool,
 }
 
-impl<'a> Iterator for EscapedStringIterator<'a> {
-    type Item = Result<char, JSONParsingError>;
+impl<'a> EscapedStringIterator<'a> {
+    /// Iterate over the string starting just after the opening `quote` in `contents`, which is at
+    /// byte `offset` in the original input
+    fn new(contents: &'a str, offset: usize, quote: char, options: ParseOptions) -> Self {
+        EscapedStringIterator {
+            contents: contents.chars(),
+            end_offset: offset + contents.len(),
+            quote,
+            json5: options.json5_enabled(),
+            done: false,
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.done {
-            None
-        } else {
-            let chr = self.contents.next();
-            match chr {
-                Some('\\') => {
-                    let chr = self.contents.next();
-                    match chr {
-                        Some('"' | '\\' | '/') => chr.map(Ok),
-                        Some('b') => Some(Ok('\x08')),
-                        Some('f') => Some(Ok('\x0c')),
-                        Some('n') => Some(Ok('\n')),
-                        Some('t') => Some(Ok('\t')),
-                        Some('r') => Some(Ok('\r')),
-                        Some('u') => {
-                            let mut get_digit = || {
-                                self.contents
-                                    .next()
-                                    .and_then(|x| x.to_digit(16))
-                                    .ok_or(JSONParsingError::TooShortEscapeSequence)
-                            };
-                            let mut parse_unicode = || {
-                                let code = [get_digit()?, get_digit()?, get_digit()?, get_digit()?];
-                                let code =
-                                    (code[0] << 12) | (code[1] << 8) | (code[2] << 4) | code[3];
-                                char::from_u32(code)
-                                    .ok_or(JSONParsingError::InvalidUnicodeEscapeSequence)
-                            };
-                            match parse_unicode() {
-                                Ok(chr) => Some(Ok(chr)),
-                                Err(e) => {
-                                    self.done = true;
-                                    Some(Err(e))
-                                }
-                            }
-                        }
-                        Some(x) => {
-                            self.done = true;
-                            Some(Err(JSONParsingError::InvalidEscapeSequence(x)))
-                        }
-                        None => None,
-                    }
+    /// Byte offset of the next unread character in the original input
+    fn offset(&self) -> usize {
+        self.end_offset - self.contents.as_str().len()
+    }
+
+    /// The next character in the input, before any escape sequences are decoded
+    fn peek_raw(&self) -> Option<char> {
+        self.contents.as_str().chars().next()
+    }
+
+    /// Read `digits` hex digits of an escape sequence
+    fn read_hex(&mut self, digits: usize) -> Result<u32, JSONParsingErrorKind> {
+        let mut code = 0;
+        for _ in 0..digits {
+            let digit = self
+                .contents
+                .next()
+                .and_then(|x| x.to_digit(16))
+                .ok_or(JSONParsingErrorKind::TooShortEscapeSequence)?;
+            code = (code << 4) | digit;
+        }
+        Ok(code)
+    }
+
+    /// Read the rest of a `\\u` escape sequence, including the second half of a surrogate pair
+    fn read_unicode_escape(&mut self) -> Result<char, JSONParsingErrorKind> {
+        let code = self.read_hex(4)?;
+        let code = match code {
+            0xD800..=0xDBFF => {
+                // A high surrogate must be immediately followed by an escaped low surrogate
+                if !self.contents.as_str().starts_with("\\u") {
+                    return Err(JSONParsingErrorKind::LoneSurrogate);
                 }
-                Some('"') => {
-                    self.done = true;
-                    None
+                self.contents.nth(1);
+                let low = self.read_hex(4)?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(JSONParsingErrorKind::LoneSurrogate);
                 }
-                None => {
-                    self.done = true;
-                    Some(Err(JSONParsingError::EndOfStream))
+                0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err(JSONParsingErrorKind::LoneSurrogate),
+            _ => code,
+        };
+        char::from_u32(code).ok_or(JSONParsingErrorKind::InvalidUnicodeEscapeSequence)
+    }
+
+    /// Read the rest of the escape sequence whose backslash is at byte `offset`
+    ///
+    /// Returns `None` for a JSON5 line continuation, which stands for no character at all.
+    fn read_escape(&mut self, offset: usize) -> Result<Option<char>, JSONParsingError> {
+        let chr = match self.contents.next() {
+            Some(chr) => chr,
+            None => return Err(JSONParsingErrorKind::EndOfStream.at(self.offset())),
+        };
+        let chr = match chr {
+            '"' | '\\' | '/' => chr,
+            'b' => '\x08',
+            'f' => '\x0c',
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'u' => self.read_unicode_escape().map_err(|e| e.at(offset))?,
+            _ if !self.json5 => {
+                return Err(JSONParsingErrorKind::InvalidEscapeSequence(chr).at(offset))
+            }
+            // JSON5 allows line breaks to be escaped, to continue the string on the next line
+            '\r' => {
+                if self.contents.as_str().starts_with('\n') {
+                    self.contents.next();
                 }
-                _ => chr.map(Ok),
+                return Ok(None);
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => return Ok(None),
+            'v' => '\x0b',
+            '0' if !matches!(self.peek_raw(), Some('0'..='9')) => '\0',
+            // Two hex digits always make a valid character
+            'x' => char::from(self.read_hex(2).map_err(|e| e.at(offset))? as u8),
+            '0'..='9' => return Err(JSONParsingErrorKind::InvalidEscapeSequence(chr).at(offset)),
+            // Any other character, including the quotes, stands for itself
+            _ => chr,
+        };
+        Ok(Some(chr))
+    }
+
+    /// Read the next character or escape sequence, which is `None` for a JSON5 line continuation
+    fn read_char(&mut self) -> Option<Result<Option<char>, JSONParsingError>> {
+        if self.done {
+            return None;
+        }
+        let offset = self.offset();
+        match self.contents.next() {
+            Some('\\') => {
+                let chr = self.read_escape(offset);
+                self.done = chr.is_err();
+                Some(chr)
+            }
+            Some(chr) if chr == self.quote => {
+                self.done = true;
+                None
+            }
+            Some(chr) => Some(Ok(Some(chr))),
+            None => {
+                self.done = true;
+                Some(Err(JSONParsingErrorKind::EndOfStream.at(offset)))
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for EscapedStringIterator<'a> {
+    type Item = Result<char, JSONParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.read_char()? {
+                Ok(Some(chr)) => return Some(Ok(chr)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
             }
         }
     }
@@ -578,9 +1471,9 @@ mod test {
 
     #[test]
     fn is_null() {
-        assert_eq!(JSONValue::load("null").is_null(), true);
-        assert_eq!(JSONValue::load("1234").is_null(), false);
-        assert_eq!(JSONValue::load("<!or").is_null(), false);
+        assert!(JSONValue::load("null").is_null());
+        assert!(!JSONValue::load("1234").is_null());
+        assert!(!JSONValue::load("<!or").is_null());
     }
 
     #[test]
@@ -588,14 +1481,15 @@ mod test {
         assert_eq!(JSONValue::load("true").read_boolean(), Ok(true));
         assert_eq!(JSONValue::load("false").read_boolean(), Ok(false));
         assert_eq!(
-            JSONValue::load("foo").read_boolean(),
-            Err(JSONParsingError::CannotParseBoolean)
+            JSONValue::load("foo").read_boolean().map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::CannotParseBoolean)
         );
     }
 
     #[test]
     fn integer() {
-        let (value, value_len) = JSONValue::parse_with_len("42").unwrap();
+        let (value, value_len) =
+            JSONValue::parse_with_len("42", 0, 0, ParseOptions::new(), false).unwrap();
         assert_eq!(value.value_type, JSONValueType::Number);
         assert_eq!(value_len, 2);
         assert_eq!(value.read_integer(), Ok(42));
@@ -606,149 +1500,610 @@ mod test {
     }
 
     #[test]
-    fn float() {
-        let (value, value_len) = JSONValue::parse_with_len("3.141592").unwrap();
-        assert_eq!(value.value_type, JSONValueType::Number);
-        assert_eq!(value_len, "3.141592".len());
+    fn number_token() {
+        fn token(s: &str) -> Result<&str, JSONParsingErrorKind> {
+            JSONValue::load(s).number_token().map_err(|e| e.kind())
+        }
+        assert_eq!(token("0"), Ok("0"));
+        assert_eq!(token("-1.25e+10 "), Ok("-1.25e+10"));
         assert_eq!(
-            value.read_integer(),
-            Err(JSONParsingError::CannotParseInteger)
+            token("1e400000000000000000000000"),
+            Ok("1e400000000000000000000000")
         );
+        for malformed in ["01", "1.", ".5", "-", "1e", "+1", "1.5.", "0x10", "1x"] {
+            assert!(token(malformed).is_err(), "{}", malformed);
+        }
+        assert_eq!(token("1.e5"), Err(JSONParsingErrorKind::UnexpectedToken));
+        assert_eq!(token("1e"), Err(JSONParsingErrorKind::EndOfStream));
         assert_eq!(
-            value.read_string(),
-            Err(JSONParsingError::CannotParseString)
+            JSONValue::load("[1, 02]")
+                .iter_array()
+                .unwrap()
+                .nth(1)
+                .unwrap()
+                .number_token(),
+            Err(JSONParsingErrorKind::UnexpectedToken.at(5))
+        );
+        assert_eq!(token("\"1\""), Err(JSONParsingErrorKind::CannotParseNumber));
+        assert_eq!(
+            ParseOptions::new()
+                .with_comments(true)
+                .load("7 // seven")
+                .number_token(),
+            Ok("7")
         );
-        assert!((value.read_float().unwrap() - 3.141592).abs() < 0.0001);
 
+        let classify = |s: &str| {
+            let value = JSONValue::load(s);
+            [
+                value.is_integer_literal(),
+                value.has_fraction(),
+                value.has_exponent(),
+                value.is_negative(),
+                value.fits_i64(),
+                value.fits_u64(),
+            ]
+        };
+        assert_eq!(classify("12"), [true, false, false, false, true, true]);
+        assert_eq!(classify("-12"), [true, false, false, true, true, false]);
+        assert_eq!(classify("-0"), [true, false, false, true, true, true]);
+        assert_eq!(classify("1.0"), [false, true, false, false, false, false]);
+        assert_eq!(classify("-1e3"), [false, false, true, true, false, false]);
+        assert_eq!(classify("2.5E-3"), [false, true, true, false, false, false]);
+        assert_eq!(
+            classify("9223372036854775808"),
+            [true, false, false, false, false, true]
+        );
         assert_eq!(
-            JSONValue::load("-3.43w").read_float(),
-            Err(JSONParsingError::CannotParseFloat)
+            classify("18446744073709551616"),
+            [true, false, false, false, false, false]
         );
+        assert_eq!(classify("012"), [false; 6]);
+        assert_eq!(classify("null"), [false; 6]);
+
+        let integral = ParseOptions::new().with_integral_floats(true).load("1e3");
+        assert!(integral.fits_i64() && integral.fits_u64());
     }
 
     #[test]
-    fn string() {
-        let (value, value_len) = JSONValue::parse_with_len("\"hello world\"").unwrap();
-        assert_eq!(value.value_type, JSONValueType::String);
-        assert_eq!(value_len, "\"hello world\"".len());
-        assert!(value.read_integer().is_err());
-        assert_eq!(value.read_string(), Ok("hello world"));
-
-        let value = JSONValue::load("\"hello world\"   ");
-        assert_eq!(value.read_string(), Ok("hello world"));
+    #[cfg(feature = "json5")]
+    fn json5_number_token() {
+        let options = ParseOptions::new().with_json5(true);
+        for s in ["+1", ".5", "5.", "0xFF", "-Infinity", "NaN"] {
+            assert_eq!(options.load(s).number_token(), Ok(s));
+        }
+        assert!(options.load("0xFF").is_integer_literal());
+        assert!(options.load("0xFF").fits_u64());
+        assert!(options.load("5.").has_fraction());
+        assert!(!options.load("-Infinity").is_integer_literal());
+        assert!(options.load("-Infinity").is_negative());
+        assert!(!options.load("NaN").fits_i64());
     }
 
     #[test]
-    fn array() {
-        let (value, value_len) = JSONValue::parse_with_len("[1,2,3]").unwrap();
-        assert_eq!(value.value_type, JSONValueType::Array);
-        assert_eq!(value_len, "[1,2,3]".len());
-        let (value, value_len) = JSONValue::parse_with_len("[]").unwrap();
-        assert_eq!(value.value_type, JSONValueType::Array);
-        assert_eq!(value_len, "[]".len());
-        let (value, value_len) = JSONValue::parse_with_len("  [\n  ]").unwrap();
-        assert_eq!(value.value_type, JSONValueType::Array);
-        assert_eq!(value_len, "  [\n  ]".len());
-        let (value, value_len) = JSONValue::parse_with_len("[1  ,  2\t,\r3\n]").unwrap();
-        assert_eq!(value.value_type, JSONValueType::Array);
-        assert_eq!(value_len, "[1  ,  2\t,\r3\n]".len());
-
-        assert!(value.read_integer().is_err());
-        assert!(value.read_string().is_err());
+    fn integral_floats() {
+        let options = ParseOptions::new().with_integral_floats(true);
+        fn read<T: JSONNumber>(options: ParseOptions, s: &str) -> Result<T, JSONParsingErrorKind> {
+            options.load(s).read_number().map_err(|e| e.kind())
+        }
+        for (s, expected) in [
+            ("1.0", 1),
+            ("1e3", 1000),
+            ("-0", 0),
+            ("-0.0", 0),
+            ("-0e-5", 0),
+            ("2.50E2", 250),
+            ("12300e-2", 123),
+            ("0.000", 0),
+        ] {
+            assert_eq!(read::<i32>(options, s), Ok(expected));
+            assert_eq!(options.load(s).read_integer(), Ok(expected as isize));
+        }
+        for fractional in ["1.5", "1e-3", "-0.1", "123e-5", "1e-999"] {
+            assert_eq!(
+                read::<i32>(options, fractional),
+                Err(JSONParsingErrorKind::NotAnInteger)
+            );
+            assert_eq!(
+                options
+                    .load(fractional)
+                    .read_integer()
+                    .map_err(|e| e.kind()),
+                Err(JSONParsingErrorKind::NotAnInteger)
+            );
+        }
+        // However many leading zeros a fraction has
+        let tiny = std::format!("0.{}1", "0".repeat(40));
         assert_eq!(
-            value.iter_array().unwrap().nth(0).unwrap().read_integer(),
-            Ok(1)
+            read::<i64>(options, &tiny),
+            Err(JSONParsingErrorKind::NotAnInteger)
         );
         assert_eq!(
-            value.iter_array().unwrap().nth(1).unwrap().read_integer(),
-            Ok(2)
+            options.load(&tiny).read_integer().map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::NotAnInteger)
         );
         assert_eq!(
-            value.iter_array().unwrap().nth(2).unwrap().read_integer(),
-            Ok(3)
+            read::<i64>(options, &std::format!("-0.{}", "0".repeat(40))),
+            Ok(0)
+        );
+        assert_eq!(read::<u8>(options, "2.55e2"), Ok(255));
+        assert_eq!(
+            read::<u8>(options, "2.56e2"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(
+            read::<u8>(options, "-1.0"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(
+            read::<i64>(options, "-9.223372036854775808e18"),
+            Ok(i64::MIN)
+        );
+        assert_eq!(read::<u128>(options, "3e38"), Ok(3 * 10u128.pow(38)));
+        assert_eq!(
+            read::<u128>(options, "1e39"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(
+            read::<i32>(options, "1.2.3"),
+            Err(JSONParsingErrorKind::CannotParseNumber)
+        );
+        assert_eq!(
+            options.load("x").read_integer().map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::CannotParseInteger)
+        );
+
+        // Without the option, these are still rejected
+        assert_eq!(
+            read::<i32>(ParseOptions::new(), "1.0"),
+            Err(JSONParsingErrorKind::NotAnInteger)
+        );
+        assert_eq!(
+            JSONValue::load("1.0").read_integer().map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::CannotParseInteger)
         );
     }
 
     #[test]
-    fn object() {
-        let input = "{
-        \"id\": 0,
-        \"name\": \"Ginger Fuller\"}";
-        let (value, value_len) = JSONValue::parse_with_len(input).unwrap();
-        assert_eq!(value.value_type, JSONValueType::Object);
-        assert_eq!(value_len, input.len());
-
-        assert!(value.read_integer().is_err());
-        assert!(value.read_string().is_err());
-        assert_eq!(value.get_key_value("id").unwrap().read_integer(), Ok(0));
+    fn read_decimal() {
+        fn read(s: &str) -> Result<(bool, u128, i32), JSONParsingErrorKind> {
+            JSONValue::load(s)
+                .read_decimal()
+                .map(|d| (d.negative, d.mantissa, d.exponent))
+                .map_err(|e| e.kind())
+        }
+        assert_eq!(read("0"), Ok((false, 0, 0)));
+        assert_eq!(read("-0.00"), Ok((true, 0, -2)));
+        assert_eq!(read("1.50"), Ok((false, 150, -2)));
+        assert_eq!(read("0.001"), Ok((false, 1, -3)));
+        assert_eq!(read("12E+3"), Ok((false, 12, 3)));
+        assert_eq!(read("7.25e-10"), Ok((false, 725, -12)));
+        assert_eq!(read("0.1 "), Ok((false, 1, -1)));
         assert_eq!(
-            value.get_key_value("name").unwrap().read_string(),
-            Ok("Ginger Fuller")
+            read("340282366920938463463374607431768211455"),
+            Ok((false, u128::MAX, 0))
+        );
+        assert_eq!(
+            read("340282366920938463463374607431768211456"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        // Trailing zeros that do not fit go in the exponent
+        assert_eq!(
+            read("1000000000000000000000000000000000000000000.0"),
+            Ok((false, 10u128.pow(38), 4))
+        );
+        assert_eq!(read("1e2147483647"), Ok((false, 1, i32::MAX)));
+        assert_eq!(
+            read("1e2147483648"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(read("1.5e-2147483647"), Ok((false, 15, i32::MIN)));
+        // Leading zeros are not part of the mantissa
+        let zeros = "0".repeat(40);
+        assert_eq!(read(&std::format!("0.{}1", zeros)), Ok((false, 1, -41)));
+        assert_eq!(read(&std::format!("-0.{}25", zeros)), Ok((true, 25, -42)));
+        assert_eq!(read(&std::format!("-0.{}", zeros)), Ok((true, 0, -40)));
+        assert_eq!(
+            read(&std::format!("0.{}", "0".repeat(400))),
+            Ok((false, 0, -400))
         );
         assert_eq!(
-            value.get_key_value("surname").err(),
-            Some(JSONParsingError::KeyNotFound)
+            read("1e99999999999999999999"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
         );
+        for malformed in ["-", "1.2.3", "1e", ".", "1x", "null", "[1]"] {
+            assert_eq!(
+                read(malformed),
+                Err(JSONParsingErrorKind::CannotParseNumber)
+            );
+        }
 
-        assert!(JSONValue::load("{\"foo\":[{}]}").value_type != JSONValueType::Error);
-        assert!(JSONValue::load("[{\"foo\":{}}]").value_type != JSONValueType::Error);
+        let fixed = |s: &str, scale| JSONValue::load(s).read_decimal().unwrap().to_fixed(scale);
+        assert_eq!(fixed("19.99", 2), Ok(1999));
+        assert_eq!(fixed("-19.99", 6), Ok(-19990000));
+        assert_eq!(fixed("1e3", 0), Ok(1000));
+        assert_eq!(fixed("0.5", 0), Err(JSONParsingErrorKind::NotAnInteger));
+        assert_eq!(fixed("1e-40", 2), Err(JSONParsingErrorKind::NotAnInteger));
+        assert_eq!(fixed("0.00", 0), Ok(0));
+        assert_eq!(fixed("0e-99", 0), Ok(0));
+        assert_eq!(
+            fixed("1e40", 0),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(
+            fixed("-170141183460469231731687303715884105728", 0),
+            Ok(i128::MIN)
+        );
+        assert_eq!(
+            fixed("170141183460469231731687303715884105728", 0),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(
+            fixed("1e2147483647", u32::MAX),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
     }
-    #[test]
 
-    fn this_broke_once() {
-        assert!(
-            JSONValue::load(
-                r##"
-[{"a":{"email":"d@"},"m":"#20\n\n.\n"}]
-    "##
-            )
-            .value_type
-                != JSONValueType::Error
-        )
-    }
+    #[test]
+    fn read_number() {
+        fn read<T: JSONNumber>(s: &str) -> Result<T, JSONParsingErrorKind> {
+            JSONValue::load(s).read_number().map_err(|e| e.kind())
+        }
+        assert_eq!(read::<i8>("-128"), Ok(-128));
+        assert_eq!(
+            read::<i8>("128"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(
+            read::<i8>("-129"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(read::<u8>("255"), Ok(255));
+        assert_eq!(read::<u8>("-0"), Ok(0));
+        assert_eq!(
+            read::<u8>("-1"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(read::<u64>("18446744073709551615"), Ok(u64::MAX));
+        assert_eq!(read::<i64>("-9223372036854775808"), Ok(i64::MIN));
+        assert_eq!(
+            read::<i128>("-170141183460469231731687303715884105728"),
+            Ok(i128::MIN)
+        );
+        assert_eq!(
+            read::<i128>("-170141183460469231731687303715884105729"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(
+            read::<u128>("340282366920938463463374607431768211455"),
+            Ok(u128::MAX)
+        );
+        assert_eq!(
+            read::<u128>("340282366920938463463374607431768211456"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(read::<usize>("007 "), Ok(7));
+        assert_eq!(read::<u32>("1.5"), Err(JSONParsingErrorKind::NotAnInteger));
+        assert_eq!(read::<u32>("1e3"), Err(JSONParsingErrorKind::NotAnInteger));
+        assert_eq!(read::<u32>("1."), Err(JSONParsingErrorKind::NotAnInteger));
+
+        for malformed in ["-", "1.2.3", "1e", "1e+", ".", "--1", "1x", "true", "\"1\""] {
+            assert_eq!(
+                read::<i32>(malformed),
+                Err(JSONParsingErrorKind::CannotParseNumber)
+            );
+            #[cfg(feature = "float")]
+            assert_eq!(
+                read::<f64>(malformed),
+                Err(JSONParsingErrorKind::CannotParseNumber)
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "float")]
+    fn read_float_number() {
+        fn read<T: JSONNumber>(s: &str) -> Result<T, JSONParsingErrorKind> {
+            JSONValue::load(s).read_number().map_err(|e| e.kind())
+        }
+        assert_eq!(read::<f64>("0.1"), Ok(0.1));
+        assert_eq!(read::<f64>("-2.5e-3"), Ok(-0.0025));
+        assert_eq!(read::<f64>("1e22"), Ok(1e22));
+        #[cfg(not(all(
+            feature = "small_number_parsing",
+            not(feature = "compact_number_parsing")
+        )))]
+        assert_eq!(read::<f64>("1e300"), Ok(1e300));
+        // The small parser is only accurate to a relative error of 1e-14
+        #[cfg(all(
+            feature = "small_number_parsing",
+            not(feature = "compact_number_parsing")
+        ))]
+        assert!((read::<f64>("1e300").unwrap() / 1e300 - 1.0).abs() < 1e-14);
+        assert_eq!(
+            read::<f64>("1e400"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(
+            read::<f32>("1e300"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(read::<f32>("12"), Ok(12.0));
+    }
+
+    #[test]
+    fn read_fixed() {
+        let scaled = |s: &str, scale| JSONValue::load(s).read_scaled(scale).map_err(|e| e.kind());
+        assert_eq!(scaled("12.345e-1", 3), Ok(1234));
+        assert_eq!(scaled("12.355e-1", 3), Ok(1236));
+        assert_eq!(scaled("12.345e-1", 5), Ok(123450));
+        assert_eq!(scaled("0.125", 2), Ok(12));
+        assert_eq!(scaled("0.135", 2), Ok(14));
+        assert_eq!(scaled("-0.135", 2), Ok(-14));
+        assert_eq!(scaled("0.1250001", 2), Ok(13));
+        assert_eq!(scaled("4e-50", 0), Ok(0));
+        assert_eq!(scaled("-9223372036854775808", 0), Ok(i64::MIN));
+        assert_eq!(
+            scaled("9223372036854775808", 0),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(
+            scaled("1e10", 10),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(scaled("x", 0), Err(JSONParsingErrorKind::CannotParseNumber));
+
+        fn q16(s: &str) -> Result<i64, JSONParsingErrorKind> {
+            JSONValue::load(s).read_fixed::<16>().map_err(|e| e.kind())
+        }
+        assert_eq!(q16("1"), Ok(1 << 16));
+        assert_eq!(q16("-2.5"), Ok(-5 << 15));
+        assert_eq!(q16("0.1"), Ok(6554));
+        assert_eq!(q16("1e2"), Ok(100 << 16));
+        // Exactly halfway between 0 and 2⁻¹⁶, and just above
+        assert_eq!(q16("0.00000762939453125"), Ok(0));
+        assert_eq!(q16("0.0000228881835937500"), Ok(2));
+        assert_eq!(
+            q16(&std::format!("0.00000762939453125{}1", "0".repeat(15))),
+            Ok(1)
+        );
+        assert_eq!(q16("1e-400"), Ok(0));
+        // Many leading zeros round away
+        let tiny = std::format!("0.{}1", "0".repeat(40));
+        assert_eq!(q16(&tiny), Ok(0));
+        assert_eq!(q16(&std::format!("-{}", tiny)), Ok(0));
+        assert_eq!(scaled(&tiny, 6), Ok(0));
+        assert_eq!(scaled(&std::format!("0.{}15", "0".repeat(40)), 41), Ok(2));
+        assert_eq!(q16("140737488355327.99999"), Ok(i64::MAX));
+        assert_eq!(
+            q16("140737488355328"),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(q16("-140737488355328"), Ok(i64::MIN));
+        // Wide intermediate values
+        assert_eq!(
+            q16("300000000000000000000000000000000000000e-38"),
+            Ok(3 << 16)
+        );
+        assert_eq!(JSONValue::load("0.5").read_fixed::<63>(), Ok(1 << 62));
+        assert_eq!(
+            JSONValue::load("1")
+                .read_fixed::<64>()
+                .map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::NumberOutOfRange)
+        );
+        assert_eq!(JSONValue::load("0").read_fixed::<64>(), Ok(0));
+        assert_eq!(JSONValue::load("3.75").read_fixed::<0>(), Ok(4));
+    }
+
+    #[test]
+    #[cfg(feature = "float")]
+    #[allow(clippy::approx_constant)]
+    fn float() {
+        let (value, value_len) =
+            JSONValue::parse_with_len("3.141592", 0, 0, ParseOptions::new(), false).unwrap();
+        assert_eq!(value.value_type, JSONValueType::Number);
+        assert_eq!(value_len, "3.141592".len());
+        assert_eq!(
+            value.read_integer().map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::CannotParseInteger)
+        );
+        assert_eq!(
+            value.read_string().map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::CannotParseString)
+        );
+        assert!((value.read_float().unwrap() - 3.141592).abs() < 0.0001);
+
+        assert_eq!(
+            JSONValue::load("-3.43w").read_float().map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::CannotParseFloat)
+        );
+    }
+
+    #[test]
+    fn string() {
+        let (value, value_len) =
+            JSONValue::parse_with_len("\"hello world\"", 0, 0, ParseOptions::new(), false).unwrap();
+        assert_eq!(value.value_type, JSONValueType::String);
+        assert_eq!(value_len, "\"hello world\"".len());
+        assert!(value.read_integer().is_err());
+        assert_eq!(value.read_string(), Ok("hello world"));
+
+        let value = JSONValue::load("\"hello world\"   ");
+        assert_eq!(value.read_string(), Ok("hello world"));
+    }
+
+    #[test]
+    fn array() {
+        let (value, value_len) =
+            JSONValue::parse_with_len("[1,2,3]", 0, 0, ParseOptions::new(), false).unwrap();
+        assert_eq!(value.value_type, JSONValueType::Array);
+        assert_eq!(value_len, "[1,2,3]".len());
+        let (value, value_len) =
+            JSONValue::parse_with_len("[]", 0, 0, ParseOptions::new(), false).unwrap();
+        assert_eq!(value.value_type, JSONValueType::Array);
+        assert_eq!(value_len, "[]".len());
+        let (value, value_len) =
+            JSONValue::parse_with_len("  [\n  ]", 0, 0, ParseOptions::new(), false).unwrap();
+        assert_eq!(value.value_type, JSONValueType::Array);
+        assert_eq!(value_len, "  [\n  ]".len());
+        let (value, value_len) =
+            JSONValue::parse_with_len("[1  ,  2\t,\r3\n]", 0, 0, ParseOptions::new(), false)
+                .unwrap();
+        assert_eq!(value.value_type, JSONValueType::Array);
+        assert_eq!(value_len, "[1  ,  2\t,\r3\n]".len());
+
+        assert!(value.read_integer().is_err());
+        assert!(value.read_string().is_err());
+        assert_eq!(
+            value.iter_array().unwrap().next().unwrap().read_integer(),
+            Ok(1)
+        );
+        assert_eq!(
+            value.iter_array().unwrap().nth(1).unwrap().read_integer(),
+            Ok(2)
+        );
+        assert_eq!(
+            value.iter_array().unwrap().nth(2).unwrap().read_integer(),
+            Ok(3)
+        );
+    }
+
+    #[test]
+    fn object() {
+        let input = "{
+        \"id\": 0,
+        \"name\": \"Ginger Fuller\"}";
+        let (value, value_len) =
+            JSONValue::parse_with_len(input, 0, 0, ParseOptions::new(), false).unwrap();
+        assert_eq!(value.value_type, JSONValueType::Object);
+        assert_eq!(value_len, input.len());
+
+        assert!(value.read_integer().is_err());
+        assert!(value.read_string().is_err());
+        assert_eq!(value.get_key_value("id").unwrap().read_integer(), Ok(0));
+        assert_eq!(
+            value.get_key_value("name").unwrap().read_string(),
+            Ok("Ginger Fuller")
+        );
+        assert_eq!(
+            value.get_key_value("surname").err().map(|e| e.kind()),
+            Some(JSONParsingErrorKind::KeyNotFound)
+        );
+
+        assert!(JSONValue::load("{\"foo\":[{}]}").value_type != JSONValueType::Error);
+        assert!(JSONValue::load("[{\"foo\":{}}]").value_type != JSONValueType::Error);
+    }
+    #[test]
+
+    fn this_broke_once() {
+        assert!(
+            JSONValue::load(
+                r##"
+[{"a":{"email":"d@"},"m":"#20\n\n.\n"}]
+    "##
+            )
+            .value_type
+                != JSONValueType::Error
+        )
+    }
 
     #[test]
     fn integer_whitespace() {
-        let (value, value_len) = JSONValue::parse_with_len("  42	").unwrap();
+        let (value, value_len) =
+            JSONValue::parse_with_len("  42	", 0, 0, ParseOptions::new(), false).unwrap();
         assert_eq!(value.value_type, JSONValueType::Number);
         assert_eq!(value_len, "  42".len());
-        let (value, value_len) = JSONValue::parse_with_len("\n 42\r").unwrap();
+        let (value, value_len) =
+            JSONValue::parse_with_len("\n 42\r", 0, 0, ParseOptions::new(), false).unwrap();
         assert_eq!(value.value_type, JSONValueType::Number);
         assert_eq!(value_len, "\n 42".len());
     }
 
     #[test]
     fn string_whitespace() {
-        let (value, value_len) = JSONValue::parse_with_len("  \"foo me a bar\"	").unwrap();
+        let (value, value_len) =
+            JSONValue::parse_with_len("  \"foo me a bar\"	", 0, 0, ParseOptions::new(), false)
+                .unwrap();
         assert_eq!(value.value_type, JSONValueType::String);
         assert_eq!(value_len, "  \"foo me a bar\"".len());
-        let (value, value_len) = JSONValue::parse_with_len("\n \"a bar\n I said.\"\r").unwrap();
+        let (value, value_len) =
+            JSONValue::parse_with_len("\n \"a bar\n I said.\"\r", 0, 0, ParseOptions::new(), false)
+                .unwrap();
         assert_eq!(value.value_type, JSONValueType::String);
         assert_eq!(value_len, "\n \"a bar\n I said.\"".len());
     }
 
     #[test]
     fn peeking_value_type() {
-        assert_eq!(JSONValue::peek_value_type("123"), JSONValueType::Number);
-        assert_eq!(JSONValue::peek_value_type("12.3"), JSONValueType::Number);
-        assert_eq!(JSONValue::peek_value_type("12.3e10"), JSONValueType::Number);
-        assert_eq!(JSONValue::peek_value_type("-3"), JSONValueType::Number);
-        assert_eq!(JSONValue::peek_value_type("-3.5"), JSONValueType::Number);
-        assert_eq!(JSONValue::peek_value_type("null"), JSONValueType::Null);
-        assert_eq!(JSONValue::peek_value_type("true"), JSONValueType::Bool);
-        assert_eq!(JSONValue::peek_value_type("false"), JSONValueType::Bool);
-        assert_eq!(JSONValue::peek_value_type("[]"), JSONValueType::Array);
-        assert_eq!(JSONValue::peek_value_type("[12]"), JSONValueType::Array);
-        assert_eq!(JSONValue::peek_value_type("[1,2]"), JSONValueType::Array);
-        assert_eq!(JSONValue::peek_value_type("[[]]"), JSONValueType::Array);
-        assert_eq!(JSONValue::peek_value_type("\"foo\""), JSONValueType::String);
-        assert_eq!(JSONValue::peek_value_type("{}"), JSONValueType::Object);
-        assert_eq!(
-            JSONValue::peek_value_type("{\"a\":2}"),
+        assert_eq!(
+            JSONValue::peek_value_type("123", ParseOptions::new()),
+            JSONValueType::Number
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("12.3", ParseOptions::new()),
+            JSONValueType::Number
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("12.3e10", ParseOptions::new()),
+            JSONValueType::Number
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("-3", ParseOptions::new()),
+            JSONValueType::Number
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("-3.5", ParseOptions::new()),
+            JSONValueType::Number
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("null", ParseOptions::new()),
+            JSONValueType::Null
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("true", ParseOptions::new()),
+            JSONValueType::Bool
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("false", ParseOptions::new()),
+            JSONValueType::Bool
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("[]", ParseOptions::new()),
+            JSONValueType::Array
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("[12]", ParseOptions::new()),
+            JSONValueType::Array
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("[1,2]", ParseOptions::new()),
+            JSONValueType::Array
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("[[]]", ParseOptions::new()),
+            JSONValueType::Array
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("\"foo\"", ParseOptions::new()),
+            JSONValueType::String
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("{}", ParseOptions::new()),
+            JSONValueType::Object
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("{\"a\":2}", ParseOptions::new()),
             JSONValueType::Object
         );
-        assert_eq!(JSONValue::peek_value_type("<"), JSONValueType::Error);
-        assert_eq!(JSONValue::peek_value_type("bar"), JSONValueType::Error);
+        assert_eq!(
+            JSONValue::peek_value_type("<", ParseOptions::new()),
+            JSONValueType::Error
+        );
+        assert_eq!(
+            JSONValue::peek_value_type("bar", ParseOptions::new()),
+            JSONValueType::Error
+        );
     }
 
     #[test]
@@ -758,6 +2113,240 @@ mod test {
         assert!(JSONValue::load_and_verify("\"foo\"").is_ok());
     }
 
+    #[test]
+    fn strict_verifying() {
+        let valid = [
+            "0",
+            "-0",
+            "-0.0e+0",
+            "12.5E-3",
+            "1e5",
+            "[1e+5, 2]",
+            "\"\\\"\\\\\\/\\b\\f\\n\\r\\t\\u00aF\"",
+            "\"£ unicode ⟼\"",
+            "\"\\ud83d\\ude00 \\uD834\\uDD1E\"",
+            "{\"\\u00e9\\ud83d\\ude00\": \"\u{7f}\"}",
+            "[]",
+            "{}",
+            " \t\r\n[ true , false , null ] ",
+            "{\"a\": {\"b\": [1, {\"c\": \"d\"}]}}",
+        ];
+        for json in valid {
+            assert!(JSONValue::load_and_verify(json).is_ok(), "{}", json);
+        }
+
+        let invalid = [
+            ("--1..e", JSONParsingErrorKind::UnexpectedToken, 1),
+            ("01", JSONParsingErrorKind::UnexpectedToken, 1),
+            ("-", JSONParsingErrorKind::EndOfStream, 1),
+            ("1.", JSONParsingErrorKind::EndOfStream, 2),
+            ("[1.]", JSONParsingErrorKind::UnexpectedToken, 3),
+            (".5", JSONParsingErrorKind::UnexpectedToken, 0),
+            ("1e", JSONParsingErrorKind::EndOfStream, 2),
+            ("1e+]", JSONParsingErrorKind::UnexpectedToken, 3),
+            ("1.5.2", JSONParsingErrorKind::UnexpectedToken, 3),
+            ("[1-2]", JSONParsingErrorKind::UnexpectedToken, 2),
+            ("\"tab\there\"", JSONParsingErrorKind::UnexpectedToken, 4),
+            (
+                "\"bad \\q\"",
+                JSONParsingErrorKind::InvalidEscapeSequence('q'),
+                5,
+            ),
+            (
+                "\"bad \\u12G4\"",
+                JSONParsingErrorKind::TooShortEscapeSequence,
+                5,
+            ),
+            (
+                "\"bad \\u12",
+                JSONParsingErrorKind::TooShortEscapeSequence,
+                5,
+            ),
+            ("\"bad \\", JSONParsingErrorKind::EndOfStream, 6),
+            ("\"\\ud83d\"", JSONParsingErrorKind::LoneSurrogate, 1),
+            ("\"\\ude00\\ud83d\"", JSONParsingErrorKind::LoneSurrogate, 1),
+            ("\"\\ud83d\\u0041\"", JSONParsingErrorKind::LoneSurrogate, 1),
+            ("{\"\\ud83d\": 1}", JSONParsingErrorKind::LoneSurrogate, 2),
+            ("\"a\nb\"", JSONParsingErrorKind::UnexpectedToken, 2),
+            ("\"\u{7f}\u{1f}\"", JSONParsingErrorKind::UnexpectedToken, 2),
+            ("\"unterminated", JSONParsingErrorKind::EndOfStream, 13),
+            ("tru", JSONParsingErrorKind::EndOfStream, 3),
+            ("nul!", JSONParsingErrorKind::UnexpectedToken, 0),
+            ("[1,]", JSONParsingErrorKind::UnexpectedToken, 3),
+            ("{\"a\":1,}", JSONParsingErrorKind::UnexpectedToken, 7),
+            ("[1", JSONParsingErrorKind::EndOfStream, 2),
+            ("[1,", JSONParsingErrorKind::EndOfStream, 3),
+            ("{", JSONParsingErrorKind::EndOfStream, 1),
+            ("[\u{a0}1]", JSONParsingErrorKind::UnexpectedToken, 1),
+            ("\u{a0}1", JSONParsingErrorKind::UnexpectedToken, 0),
+            (" \u{2003} [1]", JSONParsingErrorKind::UnexpectedToken, 1),
+            ("1\u{a0}", JSONParsingErrorKind::UnexpectedToken, 1),
+        ];
+        for (json, kind, offset) in invalid {
+            let error = JSONValue::load_and_verify(json).unwrap_err();
+            assert_eq!((error.kind(), error.offset()), (kind, offset), "{}", json);
+        }
+
+        // Only whitespace may come before or follow the top level value
+        assert!(JSONValue::load_and_verify(" \r\n\t[1,2]").is_ok());
+        assert!(JSONValue::load_prefix("\u{a0}1").is_err());
+        let error = JSONValue::load_and_verify("[1,2] xyz").unwrap_err();
+        assert_eq!(error.kind(), JSONParsingErrorKind::UnexpectedToken);
+        assert_eq!(error.offset(), 6);
+        assert!(JSONValue::load_and_verify("[1,2] \n").is_ok());
+        assert!(JSONValue::load_and_verify("1 2").is_err());
+        assert!(JSONValue::load_and_verify("").is_err());
+        assert!(JSONValue::load_and_verify("{}}").is_err());
+
+        // The lenient accessors still read things they can make sense of
+        assert_eq!(JSONValue::load("[1,]").iter_array().unwrap().count(), 1);
+        assert_eq!(JSONValue::load("[1e+5]").iter_array().unwrap().count(), 1);
+    }
+
+    #[test]
+    fn loading_prefix() {
+        let (value, rest) = JSONValue::load_prefix(" [1, 2] xyz").unwrap();
+        assert_eq!(value.value_type, JSONValueType::Array);
+        assert_eq!(value.iter_array().unwrap().count(), 2);
+        assert_eq!(rest, " xyz");
+        assert!(value.verify().is_ok());
+
+        let (value, rest) = JSONValue::load_prefix("\"a\"\"b\"").unwrap();
+        assert_eq!(value.read_string(), Ok("a"));
+        let (value, rest) = JSONValue::load_prefix(rest).unwrap();
+        assert_eq!(value.read_string(), Ok("b"));
+        assert_eq!(rest, "");
+        assert_eq!(
+            JSONValue::load_prefix(rest).map_err(|e| e.kind()).err(),
+            Some(JSONParsingErrorKind::EndOfStream)
+        );
+
+        let error = JSONValue::load_prefix("[1, 2,] [3]").unwrap_err();
+        assert_eq!(error.kind(), JSONParsingErrorKind::UnexpectedToken);
+        assert_eq!(error.offset(), 6);
+    }
+
+    #[test]
+    fn truncated_input() {
+        fn kind<T>(value: Result<T, JSONParsingError>) -> Option<JSONParsingErrorKind> {
+            value.map_err(|e| e.kind()).err()
+        }
+        assert_eq!(
+            kind(JSONValue::load("t").read_boolean()),
+            Some(JSONParsingErrorKind::CannotParseBoolean)
+        );
+        assert_eq!(
+            kind(JSONValue::load_and_verify("t")),
+            Some(JSONParsingErrorKind::EndOfStream)
+        );
+        assert_eq!(
+            kind(JSONValue::load_and_verify("[f")),
+            Some(JSONParsingErrorKind::EndOfStream)
+        );
+        assert_eq!(
+            kind(JSONValue::load("\"").read_string()),
+            Some(JSONParsingErrorKind::EndOfStream)
+        );
+        assert_eq!(JSONValue::load("[f").iter_array().unwrap().count(), 0);
+        assert_eq!(JSONValue::load("[1").iter_array().unwrap().count(), 1);
+        assert_eq!(JSONValue::load("[1£").iter_array().unwrap().count(), 1);
+
+        let next_item = |s| JSONValue::load(s).iter_object().unwrap().next();
+        assert_eq!(
+            next_item("{\"a\"").unwrap().map_err(|e| e.kind()).err(),
+            Some(JSONParsingErrorKind::EndOfStream)
+        );
+        assert_eq!(
+            next_item("{\"a\" 1}").unwrap().map_err(|e| e.kind()).err(),
+            Some(JSONParsingErrorKind::UnexpectedToken)
+        );
+        assert_eq!(
+            JSONValue::load("{\"a\":1£").iter_object().unwrap().count(),
+            1
+        );
+        // The object iterator stops at the closing bracket
+        assert_eq!(
+            JSONValue::load("{\"a\":1} \"b\":2")
+                .iter_object()
+                .unwrap()
+                .count(),
+            1
+        );
+
+        let mut value = JSONValue::load("£");
+        value.value_type = JSONValueType::Array;
+        assert_eq!(value.iter_array().unwrap().count(), 0);
+        value.value_type = JSONValueType::String;
+        assert!(value.read_string().is_err());
+    }
+
+    #[test]
+    fn depth_limit() {
+        let options = ParseOptions::new().with_max_depth(3);
+        assert!(options.load_and_verify("[[[1]], {\"a\": [2]}]").is_ok());
+        let error = options.load_and_verify("[[[[1]]]]").unwrap_err();
+        assert_eq!(error.kind(), JSONParsingErrorKind::DepthLimitExceeded);
+        assert_eq!(error.offset(), 3);
+        let error = options
+            .load_and_verify("{\"a\": {\"b\": [{}]}}")
+            .unwrap_err();
+        assert_eq!(error.kind(), JSONParsingErrorKind::DepthLimitExceeded);
+        assert_eq!(error.offset(), 13);
+        assert!(ParseOptions::new()
+            .with_max_depth(0)
+            .load_and_verify("[]")
+            .is_err());
+
+        // The limit applies to the whole document when reading lazily
+        let value = options.load("[[1, [2]], [[[3]]], 4]");
+        let first = value.iter_array().unwrap().next().unwrap();
+        assert_eq!(first.iter_array().unwrap().count(), 2);
+        assert!(first.verify().is_ok());
+        assert_eq!(value.iter_array().unwrap().count(), 1);
+        assert!(value.verify().is_err());
+
+        let value = options.load("{\"z\": 0, \"a\": {\"b\": {\"c\": {}}}}");
+        assert_eq!(value.get_key_value("z").unwrap().read_integer(), Ok(0));
+        assert_eq!(
+            value.get_key_value("a").unwrap_err().kind(),
+            JSONParsingErrorKind::DepthLimitExceeded
+        );
+        assert_eq!(
+            value.get_key_value("e").unwrap_err().kind(),
+            JSONParsingErrorKind::DepthLimitExceeded
+        );
+
+        // Limits are capped so that the scanner's state is bounded
+        assert_eq!(
+            ParseOptions::new().with_max_depth(usize::MAX).max_depth(),
+            ParseOptions::MAX_DEPTH
+        );
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let deepest = ParseOptions::new().with_max_depth(ParseOptions::MAX_DEPTH);
+        let mut input = std::string::String::new();
+        for _ in 0..ParseOptions::MAX_DEPTH / 2 {
+            input.push_str("[{\"a\":");
+        }
+        input.push('1');
+        for _ in 0..ParseOptions::MAX_DEPTH / 2 {
+            input.push_str("}]");
+        }
+        assert!(deepest.load_and_verify(&input).is_ok());
+        assert!(JSONValue::load_and_verify(&input).is_err());
+
+        // One more level is too many, and is found at the last object
+        input.insert(0, '[');
+        let error = deepest.load_and_verify(&input).unwrap_err();
+        assert_eq!(error.kind(), JSONParsingErrorKind::DepthLimitExceeded);
+        assert_eq!(
+            error.offset(),
+            1 + 6 * (ParseOptions::MAX_DEPTH / 2 - 1) + 1
+        );
+    }
+
     #[test]
     fn string_iterator() {
         let try_parse_string = |s| {
@@ -776,59 +2365,1076 @@ mod test {
         assert_eq!(value, "\"I\n\thave\x08\x0ca\\dream/\"£");
 
         let value = try_parse_string(r#" " "#);
-        assert!(matches!(value, Err(JSONParsingError::EndOfStream)));
+        assert!(matches!(value, Err(e) if e.kind() == JSONParsingErrorKind::EndOfStream));
         let value = try_parse_string(r#" "foo\" "#);
-        assert!(matches!(value, Err(JSONParsingError::EndOfStream)));
+        assert!(matches!(value, Err(e) if e.kind() == JSONParsingErrorKind::EndOfStream));
         let value = try_parse_string(r#" "Odd escape: \?" "#);
         assert!(matches!(
             value,
-            Err(JSONParsingError::InvalidEscapeSequence('?'))
+            Err(e) if e.kind() == JSONParsingErrorKind::InvalidEscapeSequence('?')
         ));
         let value = try_parse_string(r#" "\uwxyz" "#);
         assert!(matches!(
             value,
-            Err(JSONParsingError::TooShortEscapeSequence)
+            Err(e) if e.kind() == JSONParsingErrorKind::TooShortEscapeSequence
         ));
         let value = try_parse_string(r#" "\u012" "#);
         assert!(matches!(
             value,
-            Err(JSONParsingError::TooShortEscapeSequence)
+            Err(e) if e.kind() == JSONParsingErrorKind::TooShortEscapeSequence
         ));
         // This is not a single character codepoint under utf-16
         let value = try_parse_string(r#" "\ud834" "#);
         assert!(matches!(
             value,
-            Err(JSONParsingError::InvalidUnicodeEscapeSequence)
+            Err(e) if e.kind() == JSONParsingErrorKind::LoneSurrogate
         ));
     }
 
     #[test]
-    fn object_iterator() {
-        let json_value = JSONValue::load("{\"foo\" : [], \"bar\":{\"baz\": 2}}");
-        let keys = ["foo", "bar"];
-        for (item, expected_key) in json_value.iter_object().unwrap().zip(&keys) {
-            assert_eq!(item.unwrap().0, *expected_key);
+    fn read_string_into() {
+        let mut buffer = [0; 16];
+        let value = JSONValue::load(r#" "no escapes" "#);
+        let read = value.read_string_into(&mut buffer).unwrap();
+        assert_eq!(read, "no escapes");
+        // Strings without escapes are borrowed from the input
+        assert_eq!(read.as_ptr(), value.contents[1..].as_ptr());
+        assert_eq!(
+            JSONValue::load(r#""longer than the buffer""#).read_string_into(&mut []),
+            Ok("longer than the buffer")
+        );
+
+        let mut buffer = [0; 16];
+        let value = JSONValue::load(r#""a\tb😀£""#);
+        assert_eq!(value.read_string_into(&mut buffer), Ok("a\tb😀£"));
+        // The emoji does not fit, but the length of everything is still reported
+        for len in [0, 4, 6, 8] {
+            assert_eq!(
+                value.read_string_into(&mut buffer[..len]),
+                Err(JSONParsingErrorKind::BufferTooSmall(9).at(0))
+            );
         }
+        assert_eq!(value.read_string_into(&mut buffer[..9]), Ok("a\tb😀£"));
+
+        assert_eq!(
+            JSONValue::load(r#""\q""#)
+                .read_string_into(&mut buffer)
+                .map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::InvalidEscapeSequence('q'))
+        );
+        assert_eq!(
+            JSONValue::load(r#""\n"#)
+                .read_string_into(&mut buffer)
+                .map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::EndOfStream)
+        );
+        assert_eq!(
+            JSONValue::load("12")
+                .read_string_into(&mut buffer)
+                .map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::CannotParseString)
+        );
     }
 
     #[test]
-    fn string_borrow_past_lifetime_of_value() {
-        let s = "\"abc\"";
-        let t: &str;
-        {
-            t = JSONValue::load(s).read_string().unwrap();
+    #[cfg(feature = "alloc")]
+    fn read_string_cow() {
+        use alloc::borrow::Cow;
+        let value = JSONValue::load(r#" "no escapes" "#);
+        match value.read_string_cow() {
+            Ok(Cow::Borrowed(string)) => assert_eq!(string.as_ptr(), value.contents[1..].as_ptr()),
+            other => panic!("{:?} was not borrowed", other),
         }
-        assert_eq!(t, &s[1..s.len() - 1]);
+        assert_eq!(
+            JSONValue::load(r#""a\tb\ud83d\ude00""#).read_string_cow(),
+            Ok(Cow::Owned("a\tb😀".into()))
+        );
+        assert_eq!(
+            JSONValue::load(r#""\q""#).read_string_cow().unwrap_err(),
+            JSONParsingErrorKind::InvalidEscapeSequence('q').at(1)
+        );
+        assert_eq!(
+            JSONValue::load("12").read_string_cow().unwrap_err().kind(),
+            JSONParsingErrorKind::CannotParseString
+        );
     }
 
     #[test]
-    fn empty_object() {
-        let json_value = JSONValue::load("{}");
-        assert_eq!(json_value.value_type, JSONValueType::Object);
+    #[cfg(feature = "alloc")]
+    fn decoded_keys() {
+        let value = JSONValue::load(r#"{"n\u0061me": 1, "plain": [], "\x": 2}"#);
+        let mut entries = value.iter_object().unwrap().decode_keys();
+        let (key, item) = entries.next().unwrap().unwrap();
+        assert_eq!((key.as_str(), item.read_integer()), ("name", Ok(1)));
+        let (key, item) = entries.next().unwrap().unwrap();
         assert_eq!(
-            json_value.get_key_value("foo").err(),
-            Some(JSONParsingError::KeyNotFound)
+            (key.as_str(), item.value_type),
+            ("plain", JSONValueType::Array)
         );
-        assert_eq!(json_value.iter_object().unwrap().count(), 0);
+        assert_eq!(
+            entries.next().unwrap().unwrap_err(),
+            JSONParsingErrorKind::InvalidEscapeSequence('x').at(31)
+        );
+
+        // Raw and decoded keys can be mixed
+        let mut entries = value.iter_object().unwrap();
+        assert_eq!(entries.next().unwrap().unwrap().0, "n\\u0061me");
+        assert_eq!(entries.next_decoded().unwrap().unwrap().0, "plain");
+    }
+
+    #[test]
+    fn string_eq() {
+        let value = JSONValue::load(r#" "caf\u00e9 \"\ud83d\ude00\"" "#);
+        assert_eq!(value.string_eq("café \"😀\""), Ok(true));
+        assert_eq!(value.string_eq("café \"😀"), Ok(false));
+        assert_eq!(value.string_eq("café \"😀\"!"), Ok(false));
+        assert_eq!(value.string_eq("cafe"), Ok(false));
+        assert_eq!(value.string_eq(""), Ok(false));
+        assert_eq!(JSONValue::load(r#""""#).string_eq(""), Ok(true));
+        assert_eq!(JSONValue::load(r#""abc""#).string_eq("abc"), Ok(true));
+        assert_eq!(JSONValue::load(r#""abc""#).string_eq("ab"), Ok(false));
+
+        // Decoding stops at the first difference
+        let value = JSONValue::load(r#""ab\q""#);
+        assert_eq!(value.string_eq("x"), Ok(false));
+        assert_eq!(
+            value.string_eq("ab").unwrap_err(),
+            JSONParsingErrorKind::InvalidEscapeSequence('q').at(3)
+        );
+        assert_eq!(
+            JSONValue::load("12").string_eq("12").unwrap_err().kind(),
+            JSONParsingErrorKind::CannotParseString
+        );
+        assert_eq!(
+            JSONValue::load(r#""ab"#)
+                .string_eq("ab")
+                .unwrap_err()
+                .kind(),
+            JSONParsingErrorKind::EndOfStream
+        );
+    }
+
+    #[test]
+    fn escaped_keys() {
+        let value = JSONValue::load(r#"{"n\u0061me": 1, "a\/b": 2, "\ud83d\ude00": 3, "\"": 4}"#);
+        assert_eq!(value.get_key_value("name").unwrap().read_integer(), Ok(1));
+        assert_eq!(value.get_key_value("a/b").unwrap().read_integer(), Ok(2));
+        assert_eq!(value.get_key_value("😀").unwrap().read_integer(), Ok(3));
+        assert_eq!(value.get_key_value("\"").unwrap().read_integer(), Ok(4));
+        assert_eq!(
+            value.get_key_value("n\\u0061me").unwrap_err().kind(),
+            JSONParsingErrorKind::KeyNotFound
+        );
+        // The iterator still gives the keys as they appear in the input
+        let keys: std::vec::Vec<_> = value.iter_object().unwrap().map(|e| e.unwrap().0).collect();
+        assert_eq!(keys, ["n\\u0061me", "a\\/b", "\\ud83d\\ude00", "\\\""]);
+
+        // A malformed key is reported, as with any other malformed entry
+        let value = JSONValue::load(r#"{"\x": 1, "a": 2}"#);
+        assert_eq!(
+            value.get_key_value("a").unwrap_err(),
+            JSONParsingErrorKind::InvalidEscapeSequence('x').at(2)
+        );
+    }
+
+    #[test]
+    fn get_key_values() {
+        let value = JSONValue::load(r#" {"a": 1, "b": [2], "c\u0064": 3, "e": null} "#);
+        let [e, a, cd, missing] = value.get_key_values(["e", "a", "cd", "f"]).unwrap();
+        assert!(e.unwrap().is_null());
+        assert_eq!(a.unwrap().read_integer(), Ok(1));
+        assert_eq!(cd.unwrap().read_integer(), Ok(3));
+        assert!(missing.is_none());
+        assert!(value.get_key_values([]).is_ok());
+
+        // Slots are cleared first, and extra keys or slots are ignored
+        let mut values = [Some(value); 3];
+        value.get_key_values_into(&["b", "x"], &mut values).unwrap();
+        assert_eq!(values[0].unwrap().value_type, JSONValueType::Array);
+        assert!(values[1].is_none() && values[2].is_none());
+        let mut values = [None; 1];
+        value.get_key_values_into(&["a", "b"], &mut values).unwrap();
+        assert_eq!(values[0].unwrap().read_integer(), Ok(1));
+
+        // Only keys that were asked for are checked for duplicates
+        let value = JSONValue::load(r#"{"a": 1, "b": 2, "a": 3, "b\u0000": 4, "b": 5}"#);
+        assert_eq!(
+            value.get_key_values(["b"]).unwrap_err(),
+            JSONParsingErrorKind::DuplicateKey.at(44)
+        );
+        let [b] = value.get_key_values(["b\u{0}"]).unwrap();
+        assert_eq!(b.unwrap().read_integer(), Ok(4));
+
+        let value = JSONValue::load(r#"{"a": 1, "b" 2}"#);
+        assert_eq!(
+            value.get_key_values(["a"]).unwrap_err().kind(),
+            JSONParsingErrorKind::UnexpectedToken
+        );
+        assert_eq!(
+            JSONValue::load("[]")
+                .get_key_values(["a"])
+                .unwrap_err()
+                .kind(),
+            JSONParsingErrorKind::CannotParseObject
+        );
+    }
+
+    #[test]
+    fn pointer() {
+        let value = JSONValue::load(
+            r#" {"a": [10, {"b": null, "c/d": 1, "e~f": 2, "": 3, "g\u0068": 4}], " ": 5} "#,
+        );
+        let at = |pointer| value.pointer(pointer).map(|v| v.contents.trim_end());
+        assert_eq!(at(""), Ok(value.contents.trim_end()));
+        assert_eq!(at("/a/0"), Ok("10"));
+        assert_eq!(at("/a/1/b"), Ok("null"));
+        assert_eq!(at("/a/1/c~1d"), Ok("1"));
+        assert_eq!(at("/a/1/e~0f"), Ok("2"));
+        assert_eq!(at("/a/1/"), Ok("3"));
+        assert_eq!(at("/a/1/gh"), Ok("4"));
+        assert_eq!(at("/ "), Ok("5"));
+        // The pointer can continue from any value
+        let item = value.pointer("/a/1").unwrap();
+        assert_eq!(item.pointer("/e~0f").unwrap().read_integer(), Ok(2));
+
+        let error = |pointer| value.pointer(pointer).unwrap_err();
+        assert_eq!(error("/b"), JSONParsingErrorKind::PathNotFound(0).at(1));
+        assert_eq!(error("/a/2"), JSONParsingErrorKind::PathNotFound(1).at(7));
+        assert_eq!(error("/a/-"), JSONParsingErrorKind::PathNotFound(1).at(7));
+        assert_eq!(error("/a/01"), JSONParsingErrorKind::PathNotFound(1).at(7));
+        assert_eq!(error("/a/+1"), JSONParsingErrorKind::PathNotFound(1).at(7));
+        assert_eq!(
+            error("/a/99999999999999999999999"),
+            JSONParsingErrorKind::PathNotFound(1).at(7)
+        );
+        assert_eq!(error("/a/0/0"), JSONParsingErrorKind::PathNotFound(2).at(8));
+        assert_eq!(
+            error("/a/1/b/c"),
+            JSONParsingErrorKind::PathNotFound(3).at(18)
+        );
+        assert_eq!(
+            error("/a/1/c/d"),
+            JSONParsingErrorKind::PathNotFound(2).at(12)
+        );
+        assert_eq!(
+            error("/a/1/e~2f"),
+            JSONParsingErrorKind::PathNotFound(2).at(12)
+        );
+        assert_eq!(
+            error("/a/1/e~"),
+            JSONParsingErrorKind::PathNotFound(2).at(12)
+        );
+        assert_eq!(error("a"), JSONParsingErrorKind::PathNotFound(0).at(1));
+
+        // Malformed payloads are reported as they are found
+        let value = JSONValue::load(r#"{"a": [1, 2], "b" 3}"#);
+        assert_eq!(value.pointer("/a/1").unwrap().read_integer(), Ok(2));
+        assert_eq!(
+            value.pointer("/b").unwrap_err(),
+            JSONParsingErrorKind::UnexpectedToken.at(18)
+        );
+    }
+
+    #[test]
+    fn query() {
+        let value = JSONValue::load(
+            r#" {"store": {"book": [
+                {"title": "A", "price": 8.95, "tags": ["x"]},
+                {"title": "B", "price": 12.99, "isbn": "0-553"},
+                {"title": "C", "price": 8.950, "isbn": null},
+                {"title": "D", "price": 22, "title": "E"}
+            ], "bicycle": {"colour": "red", "price": 19.95}}, "a.b": [0, [1, [2]]]} "#,
+        );
+        let query = |path| -> std::vec::Vec<&str> {
+            value
+                .query(path)
+                .unwrap()
+                .map(|value| value.unwrap().contents.trim_end())
+                .collect()
+        };
+        let titles = |path| -> std::vec::Vec<&str> {
+            value
+                .query(path)
+                .unwrap()
+                .map(|value| value.unwrap().read_string().unwrap())
+                .collect()
+        };
+        assert_eq!(query("$"), [value.contents.trim_end()]);
+        assert_eq!(query("$.store.bicycle.colour"), [r#""red""#]);
+        assert_eq!(query(r#"$['store']["bicycle"].colour"#), [r#""red""#]);
+        assert_eq!(query("$['a.b'][1][1][0]"), ["2"]);
+        assert_eq!(query("$.store.bicycle.*"), [r#""red""#, "19.95"]);
+        assert_eq!(titles("$.store.book[*].title"), ["A", "B", "C", "D", "E"]);
+        assert_eq!(titles("$.store.book[1].title"), ["B"]);
+        assert_eq!(titles("$.store.book[-1].title"), ["D", "E"]);
+        assert_eq!(titles("$.store.book[-4].title"), ["A"]);
+        assert!(query("$.store.book[-5]").is_empty());
+        assert!(query("$.store.book[4]").is_empty());
+        assert_eq!(titles("$.store.book[1:3].title"), ["B", "C"]);
+        assert_eq!(titles("$.store.book[::2].title"), ["A", "C"]);
+        assert_eq!(titles("$.store.book[-2:].title"), ["C", "D", "E"]);
+        assert_eq!(titles("$.store.book[-9:1].title"), ["A"]);
+        assert_eq!(titles("$.store.book[:-3].title"), ["A"]);
+        assert!(query("$.store.book[3:1]").is_empty());
+        assert!(query("$.store.title").is_empty());
+        assert!(query("$.store.book.title").is_empty());
+
+        // Recursive descent finds values before their descendants
+        assert_eq!(query("$..price"), ["8.95", "12.99", "8.950", "22", "19.95"]);
+        assert_eq!(query("$['a.b']..[0]"), ["0", "1", "2"]);
+        assert_eq!(query("$['a.b']..*"), ["0", "[1, [2]]", "1", "[2]", "2"]);
+        assert_eq!(query("$..tags..*"), [r#""x""#]);
+
+        // Filters
+        assert_eq!(titles("$.store.book[?(@.isbn)].title"), ["B", "C"]);
+        assert_eq!(titles("$..book[?(@.price == 8.95)].title"), ["A", "C"]);
+        assert_eq!(titles("$..book[?(@.price != 8.95)].title"), ["B", "D", "E"]);
+        assert_eq!(titles("$..book[?(@.price < 12.99)].title"), ["A", "C"]);
+        assert_eq!(titles("$..book[?(@.price<=12.99)].title"), ["A", "B", "C"]);
+        assert_eq!(titles("$..book[?( @.price > 2.2e1 )].title"), [""; 0]);
+        assert_eq!(titles("$..book[?(@.price >= 2.2e1)].title"), ["D", "E"]);
+        assert_eq!(query("$..book[?(@.title == \"D\")].price"), ["22"]);
+        // As with `get_key_value`, only the first of duplicate keys is seen
+        assert!(query("$..book[?(@.title == 'E')]").is_empty());
+        assert_eq!(titles("$..book[?(@.isbn == null)].title"), ["C"]);
+        assert_eq!(titles("$..book[?(@.tags[0] == 'x')].title"), ["A"]);
+        assert_eq!(titles("$..book[?(@['title'] == 'B')].title"), ["B"]);
+        assert_eq!(titles("$..book[?(@.title == 'a)]')].title"), [""; 0]);
+        assert_eq!(query("$['a.b'][?(@ == 0)]"), ["0"]);
+        assert_eq!(query("$['a.b'][?(@[0] == 1)]"), ["[1, [2]]"]);
+        assert_eq!(query("$.store[?(@.colour)].price"), ["19.95"]);
+        // Strings are not ordered, and nor are values of different types
+        assert!(query("$..book[?(@.title < 'C')]").is_empty());
+        assert!(query("$..book[?(@.title < 1)]").is_empty());
+        assert_eq!(query("$..book[?(@.title != 1)]").len(), 4);
+
+        let error = |path| value.query(path).err().map(|e| e.kind());
+        for (path, position) in [
+            ("", 0),
+            ("store", 0),
+            ("$store", 1),
+            ("$.", 2),
+            ("$..", 3),
+            ("$...a", 3),
+            ("$.[0]", 2),
+            ("$[", 1),
+            ("$[a]", 1),
+            ("$[+1]", 1),
+            ("$[0.5]", 1),
+            ("$[]", 1),
+            ("$[1:2:0]", 1),
+            ("$[1:2:-1]", 1),
+            ("$[1:2:3:4]", 1),
+            ("$['a]", 1),
+            ("$['a'", 1),
+            ("$[?(@.a)", 1),
+            ("$[?(a)]", 4),
+            ("$[?(@.a == )]", 11),
+            ("$[?(@.a = 1)]", 8),
+            ("$[?(@.a == 01)]", 11),
+            ("$[?(@.a == 'b'c')]", 1),
+            ("$[?(@.a == \"b\" 1)]", 11),
+            ("$[?(@.a == True)]", 11),
+            ("$[?(@.a == 1e99999999999)]", 11),
+            ("$[?(@.a == 1 2 )]", 11),
+            ("$[?(@.a 1 )]", 8),
+        ] {
+            assert_eq!(
+                error(path),
+                Some(JSONParsingErrorKind::InvalidPath(position)),
+                "{}",
+                path
+            );
+        }
+
+        // Malformed payloads are reported as they are found
+        let value = JSONValue::load(r#"[{"a": 1}, {"\x": 2, "a": 5}, {"a" 3}]"#);
+        let mut results = value.query("$[*].a").unwrap();
+        assert_eq!(results.next().unwrap().unwrap().read_integer(), Ok(1));
+        assert_eq!(
+            results.next().unwrap().unwrap_err(),
+            JSONParsingErrorKind::InvalidEscapeSequence('x').at(13)
+        );
+        assert_eq!(results.next().unwrap().unwrap().read_integer(), Ok(5));
+        // As with `iter_array`, a malformed item ends the array
+        assert!(results.next().is_none());
+
+        // Filters compare numbers however small, and report numbers they cannot read
+        let tiny = std::format!("0.{}1", "0".repeat(40));
+        let input = std::format!(r#"[{{"v": {}}}, {{"v": -1}}, {{"v": "1"}}]"#, tiny);
+        let value = JSONValue::load(&input);
+        let mut results = value.query("$[?(@.v > 0)].v").unwrap();
+        assert_eq!(results.next().unwrap().unwrap().number_token(), Ok(&*tiny));
+        assert!(results.next().is_none());
+        let value = JSONValue::load(r#"[{"v": 1e99999999999}, {"v": 2}, {"\x": 3}, 4]"#);
+        let mut results = value.query("$[?(@.v > 1)]").unwrap();
+        assert_eq!(
+            results.next().unwrap().unwrap_err(),
+            JSONParsingErrorKind::NumberOutOfRange.at(7)
+        );
+        let found = results.next().unwrap().unwrap();
+        assert_eq!(found.get_key_value("v").unwrap().read_integer(), Ok(2));
+        assert_eq!(
+            results.next().unwrap().unwrap_err(),
+            JSONParsingErrorKind::InvalidEscapeSequence('x').at(35)
+        );
+        assert!(results.next().is_none());
+    }
+
+    #[test]
+    fn query_size() {
+        // A query must fit comfortably in the stack of a small microcontroller
+        assert!(core::mem::size_of::<JSONPathIterator>() <= 1024);
+    }
+
+    #[test]
+    fn query_depth() {
+        let nested = |depth| "[".repeat(depth) + "1" + &"]".repeat(depth);
+        let input = nested(JSONPathIterator::MAX_DEPTH);
+        let value = JSONValue::load(&input);
+        assert_eq!(
+            value.query("$..[0]").unwrap().count(),
+            JSONPathIterator::MAX_DEPTH
+        );
+        let results = value.query("$..*").unwrap();
+        assert_eq!(results.last().unwrap().unwrap().read_integer(), Ok(1));
+
+        let input = nested(JSONPathIterator::MAX_DEPTH + 2);
+        let value = JSONValue::load(&input);
+        let results: std::vec::Vec<_> = value.query("$..*").unwrap().collect();
+        assert_eq!(results.len(), JSONPathIterator::MAX_DEPTH + 1);
+        assert_eq!(
+            results.last().unwrap().unwrap_err(),
+            JSONParsingErrorKind::DepthLimitExceeded.at(JSONPathIterator::MAX_DEPTH)
+        );
+    }
+
+    #[test]
+    fn len_and_get_index() {
+        let value = JSONValue::load(r#" [1, "a,]\"[", [2, [3, 4]], {"b": "}", "c": [5]}, null] "#);
+        assert_eq!(value.len(), Ok(5));
+        assert_eq!(value.is_empty(), Ok(false));
+        for (index, item) in value.iter_array().unwrap().enumerate() {
+            let found = value.get_index(index).unwrap();
+            assert_eq!((found.contents, found.offset), (item.contents, item.offset));
+        }
+        assert_eq!(value.get_index(1).unwrap().read_string(), Ok("a,]\\\"["));
+        assert_eq!(
+            value.get_index(5).unwrap_err(),
+            JSONParsingErrorKind::IndexOutOfRange.at(1)
+        );
+
+        let value = JSONValue::load(r#"{"a": [1, 2], "b,": {"c": 3}}"#);
+        assert_eq!(value.len(), Ok(2));
+        assert_eq!(
+            value.get_index(0).unwrap_err().kind(),
+            JSONParsingErrorKind::CannotParseArray
+        );
+        assert_eq!(
+            JSONValue::load("12").len().map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::CannotParseArray)
+        );
+
+        for empty in ["[]", "[ ]", "{}", "{ }"] {
+            assert_eq!(JSONValue::load(empty).len(), Ok(0));
+            assert_eq!(JSONValue::load(empty).is_empty(), Ok(true));
+        }
+
+        // Preceding items are skipped without being checked
+        let value = JSONValue::load(r#"[tru, "\x", 3]"#);
+        assert_eq!(value.len(), Ok(3));
+        assert_eq!(value.get_index(2).unwrap().read_integer(), Ok(3));
+        assert_eq!(
+            value.get_index(1).unwrap().string_eq("x"),
+            Err(JSONParsingErrorKind::InvalidEscapeSequence('x').at(7))
+        );
+
+        // Unterminated payloads
+        assert_eq!(
+            JSONValue::load(r#"[1, "2]"#).len(),
+            Err(JSONParsingErrorKind::EndOfStream.at(7))
+        );
+        assert_eq!(
+            JSONValue::load("[1, [2]").get_index(1).unwrap_err(),
+            JSONParsingErrorKind::EndOfStream.at(7)
+        );
+
+        // Comments may contain brackets and quotes
+        let options = ParseOptions::new().with_comments(true);
+        let value = options.load("[1, /* ], \" */ 2 // ,]\n, 3]");
+        assert_eq!(value.len(), Ok(3));
+        assert_eq!(value.get_index(2).unwrap().read_integer(), Ok(3));
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn len_json5() {
+        let options = ParseOptions::new().with_json5(true);
+        let value = options.load("['],\\'', {a: '}'}, 3,]");
+        assert_eq!(value.len(), Ok(3));
+        assert_eq!(value.get_index(2).unwrap().read_integer(), Ok(3));
+        assert_eq!(
+            value.get_index(3).unwrap_err(),
+            JSONParsingErrorKind::IndexOutOfRange.at(0)
+        );
+        assert_eq!(options.load("{a: 1,}").len(), Ok(1));
+    }
+
+    #[test]
+    fn size_hint() {
+        // The hint must hold at every step, however malformed the input
+        fn check<I: Iterator>(make: impl Fn() -> I) {
+            let total = make().count();
+            for step in 0..=total {
+                let mut items = make();
+                items.by_ref().take(step).for_each(drop);
+                let (lower, upper) = items.size_hint();
+                let left = total - step;
+                assert!(lower <= left && upper.is_some_and(|upper| upper >= left));
+            }
+        }
+        for input in [
+            "[1, [2, 3], 4]",
+            "[1 2]",
+            "[1, 2x, 3]",
+            "[]",
+            "[1,",
+            "[[1] [2] [3]]",
+        ] {
+            check(|| JSONValue::load(input).iter_array().unwrap());
+        }
+        let objects = [
+            r#"{"a": "}", "b": {}}"#,
+            r#"{"a":1, "b""#,
+            r#"{"a":1 "b":2 "c":3}"#,
+            r#"{"a" 1}"#,
+            "{}",
+        ];
+        for input in objects {
+            check(|| JSONValue::load(input).iter_object().unwrap());
+        }
+        #[cfg(feature = "json5")]
+        check(|| {
+            ParseOptions::new()
+                .with_json5(true)
+                .load("{a:1 b:2 c:3}")
+                .iter_object()
+                .unwrap()
+        });
+
+        let value = JSONValue::load("[1, 2]");
+        let mut items = value.iter_array().unwrap();
+        items.next();
+        items.next();
+        assert_eq!(items.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn index() {
+        let value = JSONValue::load(r#" [10, "]", [1, [2]], {"a": 1}, null] "#);
+        let mut buffer = [0; 8];
+        let index = value.index_into(&mut buffer).unwrap();
+        assert_eq!(index.len(), 5);
+        assert!(!index.is_empty());
+        for (position, item) in value.iter_array().unwrap().enumerate() {
+            let found = index.get_index(position).unwrap();
+            assert_eq!((found.contents, found.offset), (item.contents, item.offset));
+        }
+        assert_eq!(
+            index.get_index(5).unwrap_err(),
+            JSONParsingErrorKind::IndexOutOfRange.at(1)
+        );
+        assert_eq!(
+            index.get_key_value("a").unwrap_err().kind(),
+            JSONParsingErrorKind::CannotParseObject
+        );
+
+        let mut buffer = [0; 4];
+        assert_eq!(
+            value.index_into(&mut buffer).unwrap_err(),
+            JSONParsingErrorKind::BufferTooSmall(5).at(1)
+        );
+        assert_eq!(
+            JSONValue::load("1").index_into(&mut buffer).unwrap_err(),
+            JSONParsingErrorKind::CannotParseArray.at(0)
+        );
+        let index = JSONValue::load("[]").index_into(&mut []).unwrap();
+        assert!(index.is_empty());
+        assert_eq!(
+            index.get_index(0).unwrap_err(),
+            JSONParsingErrorKind::IndexOutOfRange.at(0)
+        );
+    }
+
+    #[test]
+    fn index_object() {
+        let value = JSONValue::load(
+            r#"{"m": 1, "b": [2], "caf\u00e9": 3, "a": {"z": 4}, "": 5, "b": 6, "ab": 7}"#,
+        );
+        let mut buffer = [0; 7];
+        let index = value.index_into(&mut buffer).unwrap();
+        assert_eq!(index.len(), 7);
+        for (key, expected) in [("m", 1), ("café", 3), ("", 5), ("ab", 7)] {
+            assert_eq!(
+                index.get_key_value(key).unwrap().read_integer(),
+                Ok(expected)
+            );
+        }
+        // As with `get_key_value`, the first of duplicate keys is found
+        assert_eq!(
+            index.get_key_value("b").unwrap().contents,
+            value.get_key_value("b").unwrap().contents
+        );
+        assert_eq!(
+            index
+                .get_key_value("a")
+                .unwrap()
+                .get_key_value("z")
+                .unwrap()
+                .read_integer(),
+            Ok(4)
+        );
+        for key in ["c", "caf", "cafe", "n", "aa", "b "] {
+            assert_eq!(
+                index.get_key_value(key).unwrap_err(),
+                JSONParsingErrorKind::KeyNotFound.at(0)
+            );
+        }
+        assert_eq!(
+            index.get_index(0).unwrap_err().kind(),
+            JSONParsingErrorKind::CannotParseArray
+        );
+
+        // Keys are checked while building the index, and values when they are found
+        let mut buffer = [0; 4];
+        assert_eq!(
+            JSONValue::load(r#"{"a": 1, "\x": 2}"#)
+                .index_into(&mut buffer)
+                .unwrap_err(),
+            JSONParsingErrorKind::InvalidEscapeSequence('x').at(10)
+        );
+        assert_eq!(
+            JSONValue::load(r#"{"a": 1, 2: 3}"#)
+                .index_into(&mut buffer)
+                .unwrap_err(),
+            JSONParsingErrorKind::UnexpectedToken.at(9)
+        );
+        let value = JSONValue::load(r#"{"a" 1, "b": 2}"#);
+        let index = value.index_into(&mut buffer).unwrap();
+        assert_eq!(index.get_key_value("b").unwrap().read_integer(), Ok(2));
+        assert_eq!(
+            index.get_key_value("a").unwrap_err(),
+            JSONParsingErrorKind::UnexpectedToken.at(5)
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn index_vec() {
+        let value = JSONValue::load("[1, 2, 3]");
+        let index = value.index().unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.get_index(2).unwrap().read_integer(), Ok(3));
+
+        let value = JSONValue::load(r#"{"b": 1, "a": 2}"#);
+        let index = value.index().unwrap();
+        assert_eq!(index.get_key_value("a").unwrap().read_integer(), Ok(2));
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn index_json5() {
+        let options = ParseOptions::new().with_json5(true);
+        let value = options.load("{b: 1, 'a': 2, \"\\u0062b\": 3, /* c */ c: 4,}");
+        let mut buffer = [0; 4];
+        let index = value.index_into(&mut buffer).unwrap();
+        assert_eq!(index.len(), 4);
+        for (key, expected) in [("b", 1), ("a", 2), ("bb", 3), ("c", 4)] {
+            assert_eq!(
+                index.get_key_value(key).unwrap().read_integer(),
+                Ok(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn decimal_ordering() {
+        let decimal = |input| JSONValue::load(input).read_decimal().unwrap();
+        let ordered = [
+            "-1e40",
+            "-12.5",
+            "-12.49",
+            "-1",
+            "-0.5",
+            "0",
+            "-0.0",
+            "0e9",
+            "1e-40",
+            "0.1",
+            "1",
+            "1.000",
+            "10e-1",
+            "99",
+            "1e2",
+            "340282366920938463463374607431768211455",
+            "1e39",
+        ];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                let expected = match (decimal(a).mantissa == 0, decimal(b).mantissa == 0) {
+                    (true, true) => core::cmp::Ordering::Equal,
+                    _ if ["1", "1.000", "10e-1"].contains(a)
+                        && ["1", "1.000", "10e-1"].contains(b) =>
+                    {
+                        core::cmp::Ordering::Equal
+                    }
+                    _ => i.cmp(&j),
+                };
+                assert_eq!(decimal(a).cmp_value(&decimal(b)), expected, "{} {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        let try_parse_string = |s| {
+            JSONValue::load(s)
+                .iter_string()
+                .unwrap()
+                .collect::<Result<std::string::String, _>>()
+        };
+        assert_eq!(try_parse_string(r#""\ud83d\ude00""#).unwrap(), "😀");
+        assert_eq!(try_parse_string(r#""a\uD834\uDD1Eb""#).unwrap(), "a𝄞b");
+        assert_eq!(try_parse_string(r#""\udbff\udfff""#).unwrap(), "\u{10ffff}");
+        assert_eq!(
+            try_parse_string(r#""\ud7ff\ue000""#).unwrap(),
+            "\u{d7ff}\u{e000}"
+        );
+
+        let error_at = |s| {
+            let error = try_parse_string(s).unwrap_err();
+            (error.kind(), error.offset())
+        };
+        assert_eq!(
+            error_at(r#""ab\ude00""#),
+            (JSONParsingErrorKind::LoneSurrogate, 3)
+        );
+        assert_eq!(
+            error_at(r#""\ud83dx""#),
+            (JSONParsingErrorKind::LoneSurrogate, 1)
+        );
+        assert_eq!(
+            error_at(r#""\ud83d\n""#),
+            (JSONParsingErrorKind::LoneSurrogate, 1)
+        );
+        assert_eq!(
+            error_at(r#""\ud83d\u0041""#),
+            (JSONParsingErrorKind::LoneSurrogate, 1)
+        );
+        assert_eq!(
+            error_at(r#""\ud83d\ud83d""#),
+            (JSONParsingErrorKind::LoneSurrogate, 1)
+        );
+        assert_eq!(
+            error_at(r#""\ud83d"#),
+            (JSONParsingErrorKind::LoneSurrogate, 1)
+        );
+        assert_eq!(
+            error_at(r#""\ud83d\ude0""#),
+            (JSONParsingErrorKind::TooShortEscapeSequence, 1)
+        );
+    }
+
+    #[test]
+    fn object_iterator() {
+        let json_value = JSONValue::load("{\"foo\" : [], \"bar\":{\"baz\": 2}}");
+        let keys = ["foo", "bar"];
+        for (item, expected_key) in json_value.iter_object().unwrap().zip(&keys) {
+            assert_eq!(item.unwrap().0, *expected_key);
+        }
+    }
+
+    #[test]
+    fn string_borrow_past_lifetime_of_value() {
+        let s = "\"abc\"";
+        let t: &str;
+        {
+            t = JSONValue::load(s).read_string().unwrap();
+        }
+        assert_eq!(t, &s[1..s.len() - 1]);
+    }
+
+    #[test]
+    fn empty_object() {
+        let json_value = JSONValue::load("{}");
+        assert_eq!(json_value.value_type, JSONValueType::Object);
+        assert_eq!(
+            json_value.get_key_value("foo").err().map(|e| e.kind()),
+            Some(JSONParsingErrorKind::KeyNotFound)
+        );
+        assert_eq!(json_value.iter_object().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn error_offsets() {
+        let error_at = |s| {
+            let error = JSONValue::load_and_verify(s).unwrap_err();
+            (error.kind(), error.offset())
+        };
+        assert_eq!(error_at("  ?"), (JSONParsingErrorKind::UnexpectedToken, 2));
+        assert_eq!(
+            error_at("[1, 2 3]"),
+            (JSONParsingErrorKind::UnexpectedToken, 6)
+        );
+        assert_eq!(
+            error_at("[1, [2, trUe]]"),
+            (JSONParsingErrorKind::UnexpectedToken, 8)
+        );
+        assert_eq!(
+            error_at("{\"a\": 1, 2: 3}"),
+            (JSONParsingErrorKind::CannotParseString, 9)
+        );
+        assert_eq!(
+            error_at("{\"a\" 1}"),
+            (JSONParsingErrorKind::UnexpectedToken, 5)
+        );
+        assert_eq!(
+            error_at("{\"a\": 1"),
+            (JSONParsingErrorKind::EndOfStream, 7)
+        );
+
+        let value = JSONValue::load(" [true, {\"b\": 3}]");
+        let object = value.iter_array().unwrap().nth(1).unwrap();
+        assert_eq!(object.read_integer().unwrap_err().offset(), 8);
+        assert_eq!(object.get_key_value("c").unwrap_err().offset(), 8);
+        let number = object.get_key_value("b").unwrap();
+        assert_eq!(number.read_boolean().unwrap_err().offset(), 14);
+
+        let value = JSONValue::load("{\"a\": 1, \"b\": [1, }");
+        let error = value.iter_object().unwrap().nth(1).unwrap().unwrap_err();
+        assert_eq!(error.kind(), JSONParsingErrorKind::UnexpectedToken);
+        assert_eq!(error.offset(), 18);
+
+        let value = JSONValue::load("[\"abc\\qdef\"]");
+        let string = value.iter_array().unwrap().next().unwrap();
+        let error = string.iter_string().unwrap().find_map(Result::err).unwrap();
+        assert_eq!(
+            error.kind(),
+            JSONParsingErrorKind::InvalidEscapeSequence('q')
+        );
+        assert_eq!(error.offset(), 5);
+    }
+
+    #[test]
+    fn comments() {
+        let options = ParseOptions::new().with_comments(true);
+        let value = options
+            .load_and_verify(
+                "/* Leading */ {
+                    // Line comment
+                    \"a\": [1 /* one */, 2 // two
+                    ],
+                    \"b\" /**/ : /*/ tricky */ \"/* not a comment */\"
+                } // Trailing",
+            )
+            .unwrap();
+        let array = value.get_key_value("a").unwrap();
+        let items: std::vec::Vec<_> = array.iter_array().unwrap().collect();
+        assert_eq!(items[1].read_integer(), Ok(2));
+        assert_eq!(
+            value.get_key_value("b").unwrap().read_string(),
+            Ok("/* not a comment */")
+        );
+        assert_eq!(options.load("true // yes").read_boolean(), Ok(true));
+        assert_eq!(options.load("12 /* twelve */").read_integer(), Ok(12));
+        assert!(options.load("null\n// nothing").is_null());
+
+        // The rest of JSON5 is still rejected
+        let error_at = |s| {
+            let error = options.load_and_verify(s).unwrap_err();
+            (error.kind(), error.offset())
+        };
+        assert_eq!(error_at("[1,]"), (JSONParsingErrorKind::UnexpectedToken, 3));
+        assert_eq!(
+            error_at("{a: 1}"),
+            (JSONParsingErrorKind::UnexpectedToken, 1)
+        );
+        assert_eq!(
+            error_at("[1] /* open"),
+            (JSONParsingErrorKind::EndOfStream, 11)
+        );
+        assert_eq!(
+            error_at("[1 /* open"),
+            (JSONParsingErrorKind::EndOfStream, 10)
+        );
+        assert_eq!(error_at("/* open"), (JSONParsingErrorKind::EndOfStream, 7));
+        assert_eq!(
+            error_at("[1 / 2]"),
+            (JSONParsingErrorKind::UnexpectedToken, 3)
+        );
+        let value = options.load("{\"a\": 1 /* open");
+        let error = value.iter_object().unwrap().next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), JSONParsingErrorKind::EndOfStream);
+
+        // Comments are not skipped by default
+        assert!(JSONValue::load_and_verify("[1 /* one */]").is_err());
+        assert_eq!(
+            JSONValue::load("true // yes")
+                .read_boolean()
+                .map_err(|e| e.kind()),
+            Err(JSONParsingErrorKind::CannotParseBoolean)
+        );
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn json5() {
+        let options = ParseOptions::new().with_json5(true);
+        let value = options
+            .load_and_verify(
+                "// Leading comment
+                {
+                    unquoted: 'single \\'quoted\\' \"string\"',
+                    $_key2: /* inline */ [0x1F, -0XaB, +1, .5, 5., Infinity, -NaN, 1e3,],
+                    'quoted key': \"line \\
+continued\\x41\\v\\0\\q\",
+                    \"trailing\": {a: null,},
+                }
+                /* Trailing comment */
+                ",
+            )
+            .unwrap();
+        assert_eq!(value.value_type, JSONValueType::Object);
+        let keys: std::vec::Vec<_> = value.iter_object().unwrap().map(|e| e.unwrap().0).collect();
+        assert_eq!(keys, ["unquoted", "$_key2", "quoted key", "trailing"]);
+
+        let string = value.get_key_value("unquoted").unwrap();
+        assert_eq!(string.value_type, JSONValueType::String);
+        assert_eq!(string.read_string(), Ok("single \\'quoted\\' \"string\""));
+        assert_eq!(
+            string
+                .iter_string()
+                .unwrap()
+                .collect::<Result<std::string::String, _>>(),
+            Ok("single 'quoted' \"string\"".into())
+        );
+        let string = value.get_key_value("quoted key").unwrap();
+        assert_eq!(
+            string
+                .iter_string()
+                .unwrap()
+                .collect::<Result<std::string::String, _>>(),
+            Ok("line continuedA\x0b\0q".into())
+        );
+
+        let numbers: std::vec::Vec<_> = value
+            .get_key_value("$_key2")
+            .unwrap()
+            .iter_array()
+            .unwrap()
+            .collect();
+        assert_eq!(numbers.len(), 8);
+        assert!(numbers
+            .iter()
+            .all(|n| n.value_type == JSONValueType::Number));
+        assert_eq!(numbers[0].read_integer(), Ok(31));
+        assert_eq!(numbers[1].read_integer(), Ok(-171));
+        assert_eq!(numbers[2].read_integer(), Ok(1));
+        #[cfg(feature = "float")]
+        {
+            assert_eq!(numbers[1].read_float(), Ok(-171.0));
+            assert_eq!(numbers[3].read_float(), Ok(0.5));
+            assert_eq!(numbers[4].read_float(), Ok(5.0));
+            assert_eq!(numbers[5].read_float(), Ok(f32::INFINITY));
+            assert!(numbers[6].read_float().unwrap().is_nan());
+            assert_eq!(numbers[7].read_float(), Ok(1000.0));
+        }
+
+        let trailing = value.get_key_value("trailing").unwrap();
+        assert!(trailing.get_key_value("a").unwrap().is_null());
+
+        // None of this is allowed without the option
+        assert!(JSONValue::load_and_verify("{a: 1}").is_err());
+        assert!(JSONValue::load_and_verify("'a'").is_err());
+        assert!(JSONValue::load_and_verify("[1,]").is_err());
+        assert!(JSONValue::load_and_verify("0x10").is_err());
+        assert!(JSONValue::load_and_verify("[1] // comment").is_err());
+        assert!(JSONValue::load_and_verify("\"\\x41\"").is_err());
+        assert_eq!(JSONValue::load("'a'").value_type, JSONValueType::Error);
+        assert_eq!(JSONValue::load("0x10").read_integer().ok(), None);
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn json5_errors() {
+        let options = ParseOptions::new().with_json5(true);
+        let error_at = |s| {
+            let error = options.load_and_verify(s).unwrap_err();
+            (error.kind(), error.offset())
+        };
+        assert_eq!(
+            error_at("[1,,]"),
+            (JSONParsingErrorKind::UnexpectedToken, 3)
+        );
+        assert_eq!(error_at("[,]"), (JSONParsingErrorKind::UnexpectedToken, 1));
+        assert_eq!(
+            error_at("{a: 1,,}"),
+            (JSONParsingErrorKind::UnexpectedToken, 6)
+        );
+        assert_eq!(
+            error_at("{1a: 1}"),
+            (JSONParsingErrorKind::CannotParseString, 1)
+        );
+        assert_eq!(
+            error_at("{a b: 1}"),
+            (JSONParsingErrorKind::UnexpectedToken, 3)
+        );
+        assert_eq!(
+            error_at("[1 /* open"),
+            (JSONParsingErrorKind::EndOfStream, 10)
+        );
+        assert_eq!(
+            error_at("[1 / 2]"),
+            (JSONParsingErrorKind::UnexpectedToken, 3)
+        );
+        assert_eq!(error_at("0x"), (JSONParsingErrorKind::EndOfStream, 2));
+        assert_eq!(error_at("0xG"), (JSONParsingErrorKind::UnexpectedToken, 2));
+        assert_eq!(
+            error_at("0x1.5"),
+            (JSONParsingErrorKind::UnexpectedToken, 3)
+        );
+        assert_eq!(error_at("."), (JSONParsingErrorKind::EndOfStream, 1));
+        assert_eq!(error_at("+."), (JSONParsingErrorKind::EndOfStream, 2));
+        assert_eq!(error_at("01"), (JSONParsingErrorKind::UnexpectedToken, 1));
+        assert_eq!(error_at("Infin"), (JSONParsingErrorKind::EndOfStream, 5));
+        assert_eq!(
+            error_at("Infinityx"),
+            (JSONParsingErrorKind::UnexpectedToken, 8)
+        );
+        assert_eq!(error_at("1a"), (JSONParsingErrorKind::UnexpectedToken, 1));
+        assert_eq!(error_at("'abc"), (JSONParsingErrorKind::EndOfStream, 4));
+        assert_eq!(
+            error_at("'a\nb'"),
+            (JSONParsingErrorKind::UnexpectedToken, 2)
+        );
+        assert_eq!(
+            error_at("'\\1'"),
+            (JSONParsingErrorKind::InvalidEscapeSequence('1'), 1)
+        );
+        assert_eq!(
+            error_at("'\\01'"),
+            (JSONParsingErrorKind::InvalidEscapeSequence('0'), 1)
+        );
+        assert_eq!(
+            error_at("'\\x4'"),
+            (JSONParsingErrorKind::TooShortEscapeSequence, 1)
+        );
+        // JSON5 allows other control characters and more whitespace
+        assert!(options
+            .load_and_verify("\u{feff}['\t\u{1}']\u{2028}")
+            .is_ok());
+        // Comments end at any line break
+        assert!(options.load_and_verify("[1 // comment\r, 2]").is_ok());
+        assert!(options.load_and_verify("[1 /**/]").is_ok());
     }
 }
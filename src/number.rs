@@ -0,0 +1,493 @@
+//! Reading JSON numbers into Rust number types of any width
+
+use crate::{JSONParsingErrorKind, ParseOptions};
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+
+/// A number token split into its parts
+///
+/// Splitting is lenient, like the rest of the accessors: leading zeros are allowed, and either the
+/// integer or the fractional digits of a decimal number may be missing.
+pub struct NumberParts<'a> {
+    pub negative: bool,
+    pub magnitude: Magnitude<'a>,
+}
+
+/// The magnitude of a number token
+pub enum Magnitude<'a> {
+    /// A decimal number, with its digits before and after the decimal point and its exponent
+    ///
+    /// `text` is the whole magnitude, and `exponent` includes its sign, if any.
+    Decimal {
+        text: &'a str,
+        integer: &'a str,
+        fraction: &'a str,
+        exponent: &'a str,
+    },
+    /// The digits of a JSON5 hexadecimal number
+    Hexadecimal(&'a str),
+    /// JSON5 `Infinity`
+    Infinity,
+    /// JSON5 `NaN`
+    NaN,
+}
+
+/// The length of the run of ASCII digits at the start of `contents`
+fn digits_len(contents: &str) -> usize {
+    contents.bytes().take_while(u8::is_ascii_digit).count()
+}
+
+impl<'a> NumberParts<'a> {
+    /// Split a number token into its parts, returning `None` if it is not a number
+    ///
+    /// Hexadecimal numbers, `Infinity` and `NaN` are only recognised if `json5` is true.
+    pub fn split(token: &'a str, json5: bool) -> Option<Self> {
+        let (negative, text) = match token.as_bytes().first() {
+            Some(b'-') => (true, &token[1..]),
+            Some(b'+') => (false, &token[1..]),
+            _ => (false, token),
+        };
+        let magnitude = if !json5 {
+            Self::split_decimal(text)?
+        } else if text == "Infinity" {
+            Magnitude::Infinity
+        } else if text == "NaN" {
+            Magnitude::NaN
+        } else if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            Magnitude::Hexadecimal(digits)
+        } else {
+            Self::split_decimal(text)?
+        };
+        Some(NumberParts {
+            negative,
+            magnitude,
+        })
+    }
+
+    fn split_decimal(text: &'a str) -> Option<Magnitude<'a>> {
+        let integer = &text[..digits_len(text)];
+        let rest = &text[integer.len()..];
+        let (fraction, rest) = match rest.strip_prefix('.') {
+            Some(rest) => rest.split_at(digits_len(rest)),
+            None => ("", rest),
+        };
+        if integer.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        let exponent = match rest.strip_prefix(['e', 'E']) {
+            Some(exponent) => {
+                let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+                if digits.is_empty() || digits_len(digits) != digits.len() {
+                    return None;
+                }
+                exponent
+            }
+            None if rest.is_empty() => "",
+            None => return None,
+        };
+        Some(Magnitude::Decimal {
+            text,
+            integer,
+            fraction,
+            exponent,
+        })
+    }
+
+    /// The magnitude of this number, if it is written as an integer
+    ///
+    /// If `integral_floats` is true, decimal numbers with a fractional part or exponent are also
+    /// accepted if they are whole numbers.
+    fn integer_magnitude(&self, integral_floats: bool) -> Result<u128, JSONParsingErrorKind> {
+        let (digits, radix) = match self.magnitude {
+            Magnitude::Decimal { text, integer, .. } if integer.len() == text.len() => (text, 10),
+            Magnitude::Decimal { .. } if integral_floats => {
+                return self.decimal()?.scaled_magnitude(0)
+            }
+            Magnitude::Hexadecimal(digits) => (digits, 16),
+            _ => return Err(JSONParsingErrorKind::NotAnInteger),
+        };
+        // The digits have been checked, so this can only fail by overflowing
+        u128::from_str_radix(digits, radix).map_err(|_| JSONParsingErrorKind::NumberOutOfRange)
+    }
+
+    /// The exact value of this number
+    pub fn decimal(&self) -> Result<Decimal, JSONParsingErrorKind> {
+        let out_of_range = JSONParsingErrorKind::NumberOutOfRange;
+        let (mantissa, exponent) = match self.magnitude {
+            Magnitude::Decimal {
+                integer,
+                fraction,
+                exponent,
+                ..
+            } => {
+                let mut mantissa: u128 = 0;
+                // Zeros are only multiplied in once a later digit needs them, so that trailing
+                // zeros can go in the exponent instead if the mantissa is full.  Leading zeros
+                // are never needed.
+                let mut zeros: u32 = 0;
+                for digit in integer.bytes().chain(fraction.bytes()) {
+                    if digit == b'0' {
+                        zeros = zeros.saturating_add(1);
+                        continue;
+                    }
+                    if mantissa == 0 {
+                        mantissa = (digit - b'0') as u128;
+                        zeros = 0;
+                        continue;
+                    }
+                    mantissa = 10u128
+                        .checked_pow(zeros.saturating_add(1))
+                        .and_then(|power| mantissa.checked_mul(power))
+                        .and_then(|shifted| shifted.checked_add((digit - b'0') as u128))
+                        .ok_or(out_of_range)?;
+                    zeros = 0;
+                }
+                while zeros > 0 {
+                    match mantissa.checked_mul(10) {
+                        Some(shifted) => mantissa = shifted,
+                        None => break,
+                    }
+                    zeros -= 1;
+                }
+                let explicit = match exponent {
+                    "" => 0,
+                    exponent => exponent.parse::<i64>().map_err(|_| out_of_range)?,
+                };
+                let exponent = explicit
+                    .checked_sub(fraction.len() as i64)
+                    .and_then(|exponent| exponent.checked_add(zeros as i64))
+                    .and_then(|exponent| i32::try_from(exponent).ok())
+                    .ok_or(out_of_range)?;
+                (mantissa, exponent)
+            }
+            Magnitude::Hexadecimal(_) => (self.integer_magnitude(false)?, 0),
+            Magnitude::Infinity => return Err(out_of_range),
+            Magnitude::NaN => return Err(JSONParsingErrorKind::CannotParseNumber),
+        };
+        Ok(Decimal {
+            negative: self.negative,
+            mantissa,
+            exponent,
+        })
+    }
+}
+
+/// The exact value of a JSON number, `mantissa × 10^exponent`, as read by
+/// [`JSONValue::read_decimal`](crate::JSONValue::read_decimal)
+///
+/// The mantissa has the digits of the number as written, so `1.50` is 150 × 10⁻². Trailing zeros
+/// only move to the exponent if the mantissa would otherwise overflow. As a result, equal numbers
+/// may be represented differently, and compare unequal.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Decimal {
+    /// Whether the number has a minus sign, which it may have even if it is zero
+    pub negative: bool,
+    pub mantissa: u128,
+    pub exponent: i32,
+}
+
+impl Decimal {
+    /// Convert to a fixed-point integer with `scale` decimal places, that is `value × 10^scale`
+    ///
+    /// Numbers that do not fit in an `i128` give [`JSONParsingErrorKind::NumberOutOfRange`], and
+    /// numbers with more significant decimal places than `scale` give
+    /// [`JSONParsingErrorKind::NotAnInteger`] rather than being rounded.
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::{JSONValue, JSONParsingErrorKind};
+    /// let price = JSONValue::load("19.90").read_decimal().unwrap();
+    /// assert_eq!(price.to_fixed(2), Ok(1990));
+    /// assert_eq!(price.to_fixed(4), Ok(199000));
+    /// assert_eq!(price.to_fixed(1), Ok(199));
+    /// assert_eq!(price.to_fixed(0), Err(JSONParsingErrorKind::NotAnInteger));
+    /// ```
+    pub fn to_fixed(&self, scale: u32) -> Result<i128, JSONParsingErrorKind> {
+        self.signed(self.scaled_magnitude(scale)?)
+    }
+
+    /// The magnitude of [`Decimal::to_fixed`]
+    fn scaled_magnitude(&self, scale: u32) -> Result<u128, JSONParsingErrorKind> {
+        if self.mantissa == 0 {
+            return Ok(0);
+        }
+        let shift = self.exponent as i64 + scale as i64;
+        let magnitude = if shift >= 0 {
+            u32::try_from(shift)
+                .ok()
+                .and_then(|shift| 10u128.checked_pow(shift))
+                .and_then(|power| self.mantissa.checked_mul(power))
+                .ok_or(JSONParsingErrorKind::NumberOutOfRange)?
+        } else {
+            match 10u128.checked_pow(shift.unsigned_abs() as u32) {
+                Some(power) if self.mantissa.is_multiple_of(power) => self.mantissa / power,
+                // Dividing by a power of ten larger than any `u128` always leaves a remainder
+                _ => return Err(JSONParsingErrorKind::NotAnInteger),
+            }
+        };
+        Ok(magnitude)
+    }
+
+    /// Convert to an integer with `scale` decimal places, rounding to the nearest with ties going
+    /// to the even integer
+    pub(crate) fn to_scaled(self, scale: u32) -> Result<i64, JSONParsingErrorKind> {
+        let shift = self.exponent as i64 + scale as i64;
+        if shift >= 0 {
+            return i64::try_from(self.to_fixed(scale)?)
+                .map_err(|_| JSONParsingErrorKind::NumberOutOfRange);
+        }
+        let magnitude = match 10u128.checked_pow(shift.unsigned_abs() as u32) {
+            Some(power) => {
+                let quotient = self.mantissa / power;
+                round_half_even(quotient, self.mantissa % power, power, false)
+            }
+            // Less than a tenth, as the mantissa is less than 10³⁹
+            None => 0,
+        };
+        i64::try_from(self.signed(magnitude)?).map_err(|_| JSONParsingErrorKind::NumberOutOfRange)
+    }
+
+    /// Convert to a binary fixed-point integer with `frac_bits` fractional bits, that is
+    /// `value × 2^frac_bits`, rounding to the nearest with ties going to the even integer
+    pub(crate) fn to_binary_fixed(self, frac_bits: u32) -> Result<i64, JSONParsingErrorKind> {
+        let out_of_range = JSONParsingErrorKind::NumberOutOfRange;
+        if self.mantissa == 0 {
+            return Ok(0);
+        } else if frac_bits >= 64 {
+            return Err(out_of_range);
+        }
+        let magnitude = if self.exponent >= 0 {
+            10u128
+                .checked_pow(self.exponent as u32)
+                .and_then(|power| self.mantissa.checked_mul(power))
+                .and_then(|value| value.checked_mul(1 << frac_bits))
+                .ok_or(out_of_range)?
+        } else {
+            // Divide `mantissa × 2^frac_bits`, which takes up to 191 bits, by the power of ten in
+            // steps small enough that twice the remainder fits in a `u128`
+            let mut high = self.mantissa.checked_shr(128 - frac_bits).unwrap_or(0);
+            let mut low = self.mantissa << frac_bits;
+            let mut places = self.exponent.unsigned_abs();
+            let mut inexact = false;
+            loop {
+                let step = u32::min(places, 38);
+                let divisor = 10u128.pow(step);
+                let remainder = divide_wide(&mut high, &mut low, divisor);
+                places -= step;
+                if places == 0 {
+                    if high != 0 {
+                        return Err(out_of_range);
+                    }
+                    break round_half_even(low, remainder, divisor, inexact);
+                } else if high == 0 && low == 0 {
+                    // Less than a tenth is left
+                    break 0;
+                }
+                inexact |= remainder != 0;
+            }
+        };
+        i64::try_from(self.signed(magnitude)?).map_err(|_| out_of_range)
+    }
+
+    /// Compare the values of two numbers, rather than how they are written as `==` does
+    pub(crate) fn cmp_value(&self, other: &Decimal) -> Ordering {
+        let sign = |decimal: &Decimal| match (decimal.mantissa, decimal.negative) {
+            (0, _) => 0,
+            (_, true) => -1,
+            (_, false) => 1,
+        };
+        match sign(self).cmp(&sign(other)) {
+            Ordering::Equal if sign(self) != 0 => {}
+            ordering => return ordering,
+        }
+        let magnitude = self.cmp_magnitude(other);
+        if self.negative {
+            magnitude.reverse()
+        } else {
+            magnitude
+        }
+    }
+
+    /// Compare the magnitudes of two non-zero numbers
+    fn cmp_magnitude(&self, other: &Decimal) -> Ordering {
+        // The position of the leading digit decides, unless it is the same for both
+        let leading =
+            |decimal: &Decimal| decimal.mantissa.ilog10() as i64 + decimal.exponent as i64;
+        if let ordering @ (Ordering::Less | Ordering::Greater) = leading(self).cmp(&leading(other))
+        {
+            return ordering;
+        }
+        // In which case the exponents differ by less than the 39 digits of a `u128`
+        let shift = self.exponent as i64 - other.exponent as i64;
+        let scale = |mantissa: u128| {
+            10u128
+                .checked_pow(shift.unsigned_abs() as u32)
+                .and_then(|power| mantissa.checked_mul(power))
+        };
+        if shift >= 0 {
+            scale(self.mantissa).map_or(Ordering::Greater, |scaled| scaled.cmp(&other.mantissa))
+        } else {
+            scale(other.mantissa).map_or(Ordering::Less, |scaled| self.mantissa.cmp(&scaled))
+        }
+    }
+
+    /// Apply the sign to a magnitude
+    fn signed(&self, magnitude: u128) -> Result<i128, JSONParsingErrorKind> {
+        let value = if !self.negative {
+            i128::try_from(magnitude).ok()
+        } else if magnitude <= 1 << 127 {
+            Some((magnitude as i128).wrapping_neg())
+        } else {
+            None
+        };
+        value.ok_or(JSONParsingErrorKind::NumberOutOfRange)
+    }
+}
+
+/// Round `quotient + remainder / divisor` to the nearest integer, with ties going to the even one
+///
+/// The divisor must be even, and `inexact` records whether there is anything below the remainder.
+fn round_half_even(quotient: u128, remainder: u128, divisor: u128, inexact: bool) -> u128 {
+    let round_up = match (remainder * 2).cmp(&divisor) {
+        core::cmp::Ordering::Less => false,
+        core::cmp::Ordering::Equal => inexact || quotient % 2 == 1,
+        core::cmp::Ordering::Greater => true,
+    };
+    quotient + round_up as u128
+}
+
+/// Divide the 256 bit number `high:low` by `divisor` in place, returning the remainder
+///
+/// The divisor must be less than 2¹²⁷.
+fn divide_wide(high: &mut u128, low: &mut u128, divisor: u128) -> u128 {
+    let mut remainder: u128 = 0;
+    for word in [high, low] {
+        let mut quotient: u128 = 0;
+        for bit in (0..128).rev() {
+            remainder = remainder << 1 | (*word >> bit & 1);
+            if remainder >= divisor {
+                remainder -= divisor;
+                quotient |= 1 << bit;
+            }
+        }
+        *word = quotient;
+    }
+    remainder
+}
+
+/// A number type that can be read with [`JSONValue::read_number`](crate::JSONValue::read_number)
+///
+/// This is implemented for all of Rust's primitive integer types, and floating point types if the
+/// `float` feature is enabled. It cannot be implemented outside of this crate.
+pub trait JSONNumber: sealed::Sealed {}
+
+mod sealed {
+    use super::NumberParts;
+    use crate::{JSONParsingErrorKind, ParseOptions};
+
+    pub trait Sealed: Sized {
+        /// Convert a number to this type
+        fn from_parts(
+            parts: &NumberParts,
+            options: ParseOptions,
+        ) -> Result<Self, JSONParsingErrorKind>;
+    }
+}
+
+macro_rules! impl_integer {
+    ($($integer:ty),*) => {$(
+        impl JSONNumber for $integer {}
+
+        impl sealed::Sealed for $integer {
+            fn from_parts(
+                parts: &NumberParts,
+                options: ParseOptions,
+            ) -> Result<Self, JSONParsingErrorKind> {
+                let magnitude = parts.integer_magnitude(options.integral_floats())?;
+                let value = if !parts.negative {
+                    <$integer>::try_from(magnitude).ok()
+                } else if magnitude <= 1 << 127 {
+                    <$integer>::try_from((magnitude as i128).wrapping_neg()).ok()
+                } else {
+                    None
+                };
+                value.ok_or(JSONParsingErrorKind::NumberOutOfRange)
+            }
+        }
+    )*};
+}
+
+impl_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+#[cfg(all(
+    feature = "float",
+    not(any(feature = "small_number_parsing", feature = "compact_number_parsing"))
+))]
+fn parse_f32(text: &str) -> Option<f32> {
+    text.parse().ok()
+}
+
+#[cfg(all(
+    feature = "small_number_parsing",
+    not(feature = "compact_number_parsing")
+))]
+fn parse_f32(text: &str) -> Option<f32> {
+    crate::number_parsing::parse_float(text).ok()
+}
+
+#[cfg(feature = "compact_number_parsing")]
+fn parse_f32(text: &str) -> Option<f32> {
+    crate::compact_number_parsing::parse_float(text).ok()
+}
+
+#[cfg(all(
+    feature = "float",
+    not(any(feature = "small_number_parsing", feature = "compact_number_parsing"))
+))]
+fn parse_f64(text: &str) -> Option<f64> {
+    text.parse().ok()
+}
+
+#[cfg(all(
+    feature = "small_number_parsing",
+    not(feature = "compact_number_parsing")
+))]
+fn parse_f64(text: &str) -> Option<f64> {
+    crate::number_parsing::parse_double(text).ok()
+}
+
+#[cfg(feature = "compact_number_parsing")]
+fn parse_f64(text: &str) -> Option<f64> {
+    crate::compact_number_parsing::parse_double(text).ok()
+}
+
+#[cfg(feature = "float")]
+macro_rules! impl_float {
+    ($($float:ty => $parse:ident),*) => {$(
+        impl JSONNumber for $float {}
+
+        impl sealed::Sealed for $float {
+            fn from_parts(
+                parts: &NumberParts,
+                _options: ParseOptions,
+            ) -> Result<Self, JSONParsingErrorKind> {
+                let magnitude = match parts.magnitude {
+                    Magnitude::Decimal { text, .. } => $parse(text)
+                        .filter(|value| value.is_finite())
+                        // The number is well formed, so it can only be too large or small
+                        .ok_or(JSONParsingErrorKind::NumberOutOfRange)?,
+                    Magnitude::Hexadecimal(_) => parts.integer_magnitude(false)? as $float,
+                    Magnitude::Infinity => <$float>::INFINITY,
+                    Magnitude::NaN => <$float>::NAN,
+                };
+                Ok(if parts.negative { -magnitude } else { magnitude })
+            }
+        }
+    )*};
+}
+
+#[cfg(feature = "float")]
+impl_float!(f32 => parse_f32, f64 => parse_f64);
@@ -1,37 +1,87 @@
-const POWERS_OF_TEN: [f32; 39] = [
-    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
-    1e17, 1e18, 1e19, 1e20, 1e21, 1e22, 1e23, 1e24, 1e25, 1e26, 1e27, 1e28, 1e29, 1e30, 1e31, 1e32,
-    1e33, 1e34, 1e35, 1e36, 1e37, 1e38,
-];
-pub fn parse_float(value: &str) -> Result<f32, ()> {
+//! Compact float parsing, used instead of `core`'s parser by the `small_number_parsing` feature
+//!
+//! The digits are read into an integer mantissa, which is then scaled by the decimal exponent
+//! using a short table of powers of ten. Every step may round, so the result is not always the
+//! closest float to the input, but for results in the normal range the relative error is below
+//! `1e-6` for `f32` and `1e-14` for `f64`. Results outside of the normal range are rejected, as
+//! may be results within that error of its limits.
+
+use core::ops::{Div, Mul, Neg};
+
+/// The floating point types that can be parsed
+trait Float:
+    'static + Copy + PartialEq + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self>
+{
+    const ZERO: Self;
+    /// `10^(2^i)` for each `i`, for as long as these fit in the type
+    const BINARY_POWERS_OF_TEN: &'static [Self];
+
+    fn from_mantissa(mantissa: u64) -> Self;
+    fn is_normal(self) -> bool;
+}
+
+impl Float for f32 {
+    const ZERO: Self = 0.;
+    const BINARY_POWERS_OF_TEN: &'static [Self] = &[1e1, 1e2, 1e4, 1e8, 1e16, 1e32];
+
+    fn from_mantissa(mantissa: u64) -> Self {
+        mantissa as f32
+    }
+
+    fn is_normal(self) -> bool {
+        f32::is_normal(self)
+    }
+}
+
+impl Float for f64 {
+    const ZERO: Self = 0.;
+    const BINARY_POWERS_OF_TEN: &'static [Self] =
+        &[1e1, 1e2, 1e4, 1e8, 1e16, 1e32, 1e64, 1e128, 1e256];
+
+    fn from_mantissa(mantissa: u64) -> Self {
+        mantissa as f64
+    }
+
+    fn is_normal(self) -> bool {
+        f64::is_normal(self)
+    }
+}
+
+/// Digits are only added to the mantissa while it is below this, so that it fits in a `u64`
+const MANTISSA_LIMIT: u64 = 1_000_000_000_000_000_000;
+
+fn parse<F: Float>(value: &str) -> Result<F, ()> {
     let mut chars = value.chars().peekable();
     let positive = match chars.peek() {
         Some('-') => {
             chars.next();
-            if chars.peek().is_none() {
-                return Err(());
-            }
             false
         }
         Some(_) => true,
         _ => return Err(()),
     };
-    let mut value = 0.;
-    let mut next_digit_size = 0.1;
-    let mut exponent = 0;
+    let mut mantissa: u64 = 0;
+    // The power of ten the mantissa must be multiplied by
+    let mut exponent: i32 = 0;
+    let mut seen_digit = false;
     let mut seen_decimal = false;
     let mut seen_exponent = false;
-    while let Some(c) = chars.next() {
+    for c in chars.by_ref() {
         match c {
             '0'..='9' => {
-                if !seen_decimal {
-                    exponent += 1;
+                seen_digit = true;
+                if mantissa < MANTISSA_LIMIT {
+                    mantissa = mantissa * 10 + c.to_digit(10).unwrap() as u64;
+                    if seen_decimal {
+                        exponent -= 1;
+                    }
+                } else if !seen_decimal {
+                    // Digits that don't fit are dropped, but still count towards the magnitude
+                    exponent = exponent.saturating_add(1);
                 }
-                value += next_digit_size * c.to_digit(10).unwrap() as f32;
-                next_digit_size *= 0.1;
             }
             '.' => {
-                if seen_decimal || seen_exponent {
+                if seen_decimal {
                     return Err(());
                 } else {
                     seen_decimal = true;
@@ -44,18 +94,26 @@ pub fn parse_float(value: &str) -> Result<f32, ()> {
             _ => return Err(()),
         }
     }
+    if !seen_digit {
+        return Err(());
+    }
     if seen_exponent {
-        let mut explicit_exponent = 0;
+        let mut explicit_exponent: i32 = 0;
         let exponent_positive = match chars.peek() {
             Some('-' | '+') => chars.next() == Some('+'),
             Some('0'..='9') => true,
             _ => return Err(()),
         };
+        if chars.peek().is_none() {
+            return Err(());
+        }
         for c in chars {
             match c {
                 '0'..='9' => {
-                    explicit_exponent *= 10;
-                    explicit_exponent += c.to_digit(10).unwrap() as i32;
+                    // Saturate rather than overflow, as the result is out of range either way
+                    explicit_exponent = explicit_exponent
+                        .saturating_mul(10)
+                        .saturating_add(c.to_digit(10).unwrap() as i32);
                 }
                 _ => return Err(()),
             }
@@ -63,15 +121,30 @@ pub fn parse_float(value: &str) -> Result<f32, ()> {
         if !exponent_positive {
             explicit_exponent = -explicit_exponent;
         }
-        exponent += explicit_exponent;
+        exponent = exponent.saturating_add(explicit_exponent);
     }
-    if exponent < -37 || exponent > 38 {
-        return Err(());
-    }
-    if 0 < exponent {
-        value = value * POWERS_OF_TEN[exponent as usize];
-    } else if exponent < 0 {
-        value = value / POWERS_OF_TEN[exponent.abs() as usize];
+
+    let mut value = F::from_mantissa(mantissa);
+    if value != F::ZERO {
+        // The mantissa is less than 10^19, so larger powers can never be in range
+        let scale = exponent.unsigned_abs();
+        if scale >> F::BINARY_POWERS_OF_TEN.len() != 0 {
+            return Err(());
+        }
+        // Scaling from the largest power down means intermediate values only overflow or
+        // underflow if the result does
+        for (bit, power) in F::BINARY_POWERS_OF_TEN.iter().enumerate().rev() {
+            if scale & (1 << bit) != 0 {
+                if exponent > 0 {
+                    value = value * *power;
+                } else {
+                    value = value / *power;
+                }
+            }
+        }
+        if !value.is_normal() {
+            return Err(());
+        }
     }
 
     if !positive {
@@ -80,10 +153,21 @@ pub fn parse_float(value: &str) -> Result<f32, ()> {
     Ok(value)
 }
 
+/// Parse a float, less accurately than `core` but with a much smaller code footprint
+pub fn parse_float(value: &str) -> Result<f32, ()> {
+    parse(value)
+}
+
+/// Parse a double, less accurately than `core` but with a much smaller code footprint
+pub fn parse_double(value: &str) -> Result<f64, ()> {
+    parse(value)
+}
+
 #[cfg(test)]
 mod test {
     extern crate std;
-    use super::parse_float;
+    use super::{parse_double, parse_float};
+    use std::format;
     use std::string::ToString;
 
     fn close(a: f32, b: f32) -> bool {
@@ -133,6 +217,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn manual() {
         assert!(close(parse_float("3.141592").unwrap(), 3.141592f32));
         assert!(close(parse_float("3.141592e-2").unwrap(), 0.03141592f32));
@@ -163,5 +248,70 @@ mod test {
         assert!(parse_float("10000000000000000000000000000000000000000").is_err());
         assert!(parse_float("-10000000000000000000000000000000000000000").is_err());
         assert!(parse_float("1e-39").is_err());
+        assert!(parse_float("1e99999999999999").is_err());
+        assert!(parse_float("-1e-99999999999999").is_err());
+    }
+
+    /// Check the relative error against `core`'s parser, which is correctly rounded
+    fn accurate_f32(s: &str) -> bool {
+        let expected: f32 = s.parse().unwrap();
+        parse_float(s).is_ok_and(|value| f32::abs((value - expected) / expected) < 1e-6)
+    }
+
+    fn accurate_f64(s: &str) -> bool {
+        let expected: f64 = s.parse().unwrap();
+        parse_double(s).is_ok_and(|value| f64::abs((value - expected) / expected) < 1e-14)
+    }
+
+    #[test]
+    fn accuracy() {
+        // A simple linear congruential generator gives a spread of mantissas
+        let mut seed: u64 = 1;
+        for _ in 0..20000 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let mantissa = seed >> 11;
+            let digits = mantissa.to_string().len() as i32;
+            let exponent = (seed % 600) as i32 - 300;
+            let s = format!("{}e{}", mantissa, exponent);
+            if (-300..300).contains(&(digits + exponent)) {
+                assert!(accurate_f64(&s), "{}", s);
+            }
+            if (-36..38).contains(&(digits + exponent)) {
+                assert!(accurate_f32(&s), "{}", s);
+            }
+            let s = format!("-0.{:0>20}", mantissa);
+            assert!(accurate_f64(&s), "{}", s);
+            assert!(accurate_f32(&s), "{}", s);
+        }
+        for s in [
+            "0.3",
+            "0.1",
+            "2.2250738585072014e-308",
+            "1.79e308",
+            "4.9406564584124654e-300",
+            "123456789012345678901234567890",
+            "0.000000000000000000000000000001",
+            "3.141592653589793238462643383279",
+        ] {
+            assert!(accurate_f64(s), "{}", s);
+        }
+    }
+
+    #[test]
+    fn doubles() {
+        assert_eq!(parse_double("0").unwrap(), 0f64);
+        assert_eq!(parse_double("-0e-400").unwrap(), 0f64);
+        assert!(accurate_f64("1e308"));
+        assert!(accurate_f64("-1e-300"));
+        assert_eq!(parse_double("12.5").unwrap(), 12.5);
+        assert!(parse_double("1e309").is_err());
+        assert!(parse_double("1e-320").is_err());
+        assert!(parse_double("1e99999999999999").is_err());
+        assert!(parse_double("").is_err());
+        assert!(parse_double(".").is_err());
+        assert!(parse_double("1e").is_err());
+        assert!(parse_double("1.2.3").is_err());
     }
 }
@@ -0,0 +1,221 @@
+use crate::scan::whitespace_len;
+use crate::{JSONParsingError, JSONParsingErrorKind, JSONValue, JSONValueMut};
+
+/// Options controlling how JSON payloads are scanned
+///
+/// The options are carried along with every [`JSONValue`] loaded with them, so they also apply
+/// when iterating through or searching the value later.
+///
+/// ### Example
+/// ```
+/// # use microjson::{ParseOptions, JSONParsingErrorKind};
+/// let options = ParseOptions::new().with_max_depth(2);
+/// assert!(options.load_and_verify("[[1, 2], [3]]").is_ok());
+/// assert_eq!(
+///     options.load_and_verify("[[[1]]]").unwrap_err().kind(),
+///     JSONParsingErrorKind::DepthLimitExceeded
+/// );
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    max_depth: usize,
+    comments: bool,
+    #[cfg(feature = "json5")]
+    json5: bool,
+    integral_floats: bool,
+}
+
+impl ParseOptions {
+    /// The default limit on how deeply arrays and objects may be nested
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// The largest nesting limit supported
+    ///
+    /// Scanning is not recursive, and instead keeps one bit of state per level of nesting on the
+    /// stack. This bounds the size of that state.
+    pub const MAX_DEPTH: usize = 1024;
+
+    /// The default options
+    pub const fn new() -> Self {
+        ParseOptions {
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            comments: false,
+            #[cfg(feature = "json5")]
+            json5: false,
+            integral_floats: false,
+        }
+    }
+
+    /// Limit how deeply arrays and objects may be nested
+    ///
+    /// Exceeding this limit results in a
+    /// [`JSONParsingErrorKind::DepthLimitExceeded`](crate::JSONParsingErrorKind::DepthLimitExceeded)
+    /// error. A limit of zero forbids arrays and objects entirely. Limits above
+    /// [`ParseOptions::MAX_DEPTH`] are reduced to it.
+    pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = if max_depth < Self::MAX_DEPTH {
+            max_depth
+        } else {
+            Self::MAX_DEPTH
+        };
+        self
+    }
+
+    /// How deeply arrays and objects may be nested
+    pub const fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Skip `//` line comments and `/* */` block comments wherever whitespace is allowed
+    ///
+    /// This accepts JSON with comments, as used by the configuration files of many editors,
+    /// without accepting the rest of JSON5. An unterminated block comment results in a
+    /// [`JSONParsingErrorKind::EndOfStream`](crate::JSONParsingErrorKind::EndOfStream) error.
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::{ParseOptions, JSONParsingError};
+    /// # fn main() -> Result<(), JSONParsingError> {
+    /// let settings = r#"{
+    ///     // Spaces, not tabs
+    ///     "tabSize": 4 /* columns */
+    /// }"#;
+    /// let value = ParseOptions::new().with_comments(true).load_and_verify(settings)?;
+    /// assert_eq!(value.get_key_value("tabSize")?.read_integer()?, 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub const fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Whether comments are skipped
+    ///
+    /// Comments are also skipped if JSON5 is enabled, regardless of this option.
+    pub const fn comments(&self) -> bool {
+        self.comments
+    }
+
+    /// Whether comments are skipped, either on their own or as part of JSON5
+    pub(crate) const fn comments_enabled(&self) -> bool {
+        self.comments || self.json5_enabled()
+    }
+
+    /// Accept the relaxed syntax of [JSON5](https://spec.json5.org/)
+    ///
+    /// This allows comments, trailing commas, single quoted strings, unquoted object keys,
+    /// hexadecimal numbers, `Infinity`, `NaN`, leading `+` signs, leading and trailing decimal
+    /// points, the extra escape sequences and line continuations of JavaScript strings and the
+    /// extra whitespace characters of JavaScript. Unquoted keys may not contain escape sequences.
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::{ParseOptions, JSONParsingError};
+    /// # fn main() -> Result<(), JSONParsingError> {
+    /// let config = "{
+    ///     // Comments are allowed
+    ///     name: 'sensor',
+    ///     mask: 0xFF,
+    ///     limits: [+1, .5, Infinity,],
+    /// }";
+    /// let value = ParseOptions::new().with_json5(true).load_and_verify(config)?;
+    /// assert_eq!(value.get_key_value("name")?.read_string()?, "sensor");
+    /// assert_eq!(value.get_key_value("mask")?.read_integer()?, 255);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "json5")]
+    pub const fn with_json5(mut self, json5: bool) -> Self {
+        self.json5 = json5;
+        self
+    }
+
+    /// Whether the relaxed syntax of JSON5 is accepted
+    #[cfg(feature = "json5")]
+    pub const fn json5(&self) -> bool {
+        self.json5
+    }
+
+    /// Whether the relaxed syntax of JSON5 is accepted, which is never the case unless the `json5`
+    /// feature is enabled
+    #[cfg(feature = "json5")]
+    pub(crate) const fn json5_enabled(&self) -> bool {
+        self.json5
+    }
+
+    #[cfg(not(feature = "json5"))]
+    pub(crate) const fn json5_enabled(&self) -> bool {
+        false
+    }
+
+    /// Accept numbers written with a fractional part or exponent when reading integers, as long
+    /// as they are whole numbers
+    ///
+    /// Many producers, JavaScript in particular, write whole numbers such as `1.0` or `1e3`. With
+    /// this option they can be read with [`JSONValue::read_integer`] and
+    /// [`JSONValue::read_number`], while numbers that are not whole still result in a
+    /// [`JSONParsingErrorKind::NotAnInteger`](crate::JSONParsingErrorKind::NotAnInteger) error.
+    ///
+    /// ### Example
+    /// ```
+    /// # use microjson::{ParseOptions, JSONParsingErrorKind};
+    /// let options = ParseOptions::new().with_integral_floats(true);
+    /// assert_eq!(options.load("2.50e2").read_number::<u16>(), Ok(250));
+    /// assert_eq!(
+    ///     options.load("2.5").read_number::<u16>().unwrap_err().kind(),
+    ///     JSONParsingErrorKind::NotAnInteger
+    /// );
+    /// ```
+    pub const fn with_integral_floats(mut self, integral_floats: bool) -> Self {
+        self.integral_floats = integral_floats;
+        self
+    }
+
+    /// Whether whole numbers with a fractional part or exponent can be read as integers
+    pub const fn integral_floats(&self) -> bool {
+        self.integral_floats
+    }
+
+    /// Create a new [`JSONValue`] from an input string using these options
+    ///
+    /// See [`JSONValue::load`].
+    pub fn load(self, contents: &str) -> JSONValue<'_> {
+        JSONValue::load_with_options(contents, self)
+    }
+
+    /// Load a JSON value from a payload using these options and verify that it is valid JSON
+    ///
+    /// See [`JSONValue::load_and_verify`].
+    pub fn load_and_verify(self, contents: &str) -> Result<JSONValue<'_>, JSONParsingError> {
+        let value = self.load(contents);
+        // Loading skips any whitespace before the value, which must be checked here as `verify`
+        // cannot see it
+        let whitespace = whitespace_len(contents, 0, self, true)?;
+        if whitespace < value.offset {
+            return Err(JSONParsingErrorKind::UnexpectedToken.at(whitespace));
+        }
+        value.verify()?;
+        Ok(value)
+    }
+
+    /// Create a new [`JSONValueMut`] from a mutable input buffer using these options
+    ///
+    /// See [`JSONValueMut::load`].
+    pub fn load_mut(self, buffer: &mut [u8]) -> Result<JSONValueMut<'_>, JSONParsingError> {
+        JSONValueMut::load_with_options(buffer, self)
+    }
+
+    /// Load and verify the JSON value at the start of a payload using these options
+    ///
+    /// See [`JSONValue::load_prefix`].
+    pub fn load_prefix(self, contents: &str) -> Result<(JSONValue<'_>, &str), JSONParsingError> {
+        JSONValue::load_prefix_with_options(contents, self)
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
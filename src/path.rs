@@ -0,0 +1,624 @@
+//! Paths into a JSON payload: JSON Pointers, and queries in a subset of JSONPath
+//!
+//! Queries are never parsed into a tree, which would need allocating.  Instead, each segment is
+//! read from the path text when it is needed, and the search keeps a fixed size stack of the
+//! arrays and objects it is part way through.
+
+use crate::number::{Decimal, NumberParts};
+use crate::scan::strict_number_len;
+use crate::{
+    JSONArrayIterator, JSONObjectIterator, JSONParsingError, JSONParsingErrorKind, JSONValue,
+    JSONValueType, ObjectKey,
+};
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+
+/// A segment of a JSON Pointer, which iterates over its characters with `~0` and `~1` decoded
+#[derive(Clone)]
+pub(crate) struct PointerToken<'a> {
+    raw: &'a str,
+    chars: core::str::Chars<'a>,
+}
+
+impl<'a> PointerToken<'a> {
+    /// Read the segment `raw`, which must not contain a `/`, or `None` if it has an invalid `~`
+    /// escape
+    pub(crate) fn new(raw: &'a str) -> Option<Self> {
+        let mut escapes = raw.split('~').skip(1);
+        if escapes.any(|rest| !(rest.starts_with('0') || rest.starts_with('1'))) {
+            return None;
+        }
+        Some(PointerToken {
+            raw,
+            chars: raw.chars(),
+        })
+    }
+
+    /// The array index this segment refers to, if it is one
+    ///
+    /// Indices are decimal numbers without leading zeros.  The `-` index, which refers to the
+    /// element after the end of an array, never exists when reading.
+    pub(crate) fn index(&self) -> Option<usize> {
+        let raw = self.raw;
+        if raw.is_empty() || !raw.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        if raw.len() > 1 && raw.starts_with('0') {
+            return None;
+        }
+        raw.parse().ok()
+    }
+}
+
+impl Iterator for PointerToken<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        match self.chars.next()? {
+            // Escapes were checked when the token was made
+            '~' => match self.chars.next() {
+                Some('0') => Some('~'),
+                _ => Some('/'),
+            },
+            chr => Some(chr),
+        }
+    }
+}
+
+/// A comparison in a JSONPath filter
+#[derive(Copy, Clone)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Comparison {
+    const OPERATORS: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<=", Comparison::LessEqual),
+        (">=", Comparison::GreaterEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+    ];
+
+    /// Whether a value that is `equal` to the literal, and ordered as `ordering` if it is
+    /// comparable with it, passes
+    fn passes(self, equal: bool, ordering: Option<Ordering>) -> bool {
+        match self {
+            Comparison::Equal => equal,
+            Comparison::NotEqual => !equal,
+            Comparison::Less => ordering == Some(Ordering::Less),
+            Comparison::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Comparison::Greater => ordering == Some(Ordering::Greater),
+            Comparison::GreaterEqual => {
+                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+            }
+        }
+    }
+}
+
+/// A JSONPath filter, `?(@.path)` or `?(@.path <comparison> <literal>)`
+#[derive(Copy, Clone)]
+struct Filter<'p> {
+    /// The path after `@` to the value that is tested
+    path: &'p str,
+    /// The comparison and the literal it is made with, which has been checked to be a string,
+    /// number, `true`, `false` or `null`
+    comparison: Option<(Comparison, &'p str)>,
+}
+
+impl<'p> Filter<'p> {
+    /// Read the filter expression between `?(` and `)`, or return the position in `expression` at
+    /// which it is malformed
+    fn parse(expression: &'p str) -> Result<Self, usize> {
+        let start = expression.len() - expression.trim_start().len();
+        let mut rest = expression[start..].strip_prefix('@').ok_or(start)?;
+        let path_len = rest.len();
+        while let Some((_, len)) = relative_step(rest) {
+            rest = &rest[len..];
+        }
+        let path = &expression[start + 1..start + 1 + path_len - rest.len()];
+        // The position of a part of the expression
+        let position = |part: &str| part.as_ptr() as usize - expression.as_ptr() as usize;
+        let rest = rest.trim_start();
+        if rest.trim_end().is_empty() {
+            return Ok(Filter {
+                path,
+                comparison: None,
+            });
+        }
+        let (comparison, literal) = Comparison::OPERATORS
+            .iter()
+            .find_map(|&(operator, comparison)| {
+                Some((
+                    comparison,
+                    rest.strip_prefix(operator)?.trim_start().trim_end(),
+                ))
+            })
+            .ok_or_else(|| position(rest))?;
+        let valid = match literal.as_bytes() {
+            [quote @ (b'"' | b'\''), inner @ .., end] => end == quote && !inner.contains(quote),
+            _ => {
+                matches!(literal, "true" | "false" | "null")
+                    || (strict_number_len(literal, 0, false) == Ok(literal.len())
+                        && number_literal(literal).is_some())
+            }
+        };
+        if !valid {
+            return Err(position(literal));
+        }
+        Ok(Filter {
+            path,
+            comparison: Some((comparison, literal)),
+        })
+    }
+
+    /// Whether `value` passes the filter
+    ///
+    /// Values that are missing, or of a type that cannot be compared with the literal, do not
+    /// pass.  Objects on the path and numbers that are malformed are reported as errors.
+    fn test(&self, mut value: JSONValue<'_>) -> Result<bool, JSONParsingError> {
+        let mut path = self.path;
+        while let Some((step, len)) = relative_step(path) {
+            let next = match step {
+                Step::Key(key) if value.value_type == JSONValueType::Object => {
+                    value.find_key(key.chars())?
+                }
+                Step::Key(_) => None,
+                Step::Index(index) => value
+                    .iter_array()
+                    .ok()
+                    .and_then(|mut items| items.nth(index)),
+            };
+            value = match next {
+                Some(next) => next,
+                None => return Ok(false),
+            };
+            path = &path[len..];
+        }
+        let (comparison, literal) = match self.comparison {
+            Some(comparison) => comparison,
+            None => return Ok(true),
+        };
+        let (equal, ordering) = match literal {
+            "true" | "false" => (value.read_boolean() == Ok(literal == "true"), None),
+            "null" => (value.is_null(), None),
+            _ if literal.starts_with(['"', '\'']) => {
+                let string = &literal[1..literal.len() - 1];
+                (value.string_eq(string) == Ok(true), None)
+            }
+            _ if value.value_type == JSONValueType::Number => {
+                let number = value.read_decimal()?;
+                // The literal was checked when the filter was read
+                let ordering = number_literal(literal).map(|literal| number.cmp_value(&literal));
+                (ordering == Some(Ordering::Equal), ordering)
+            }
+            _ => (false, None),
+        };
+        Ok(comparison.passes(equal, ordering))
+    }
+}
+
+/// The value of a number literal in a filter, or `None` if it is too large or precise
+fn number_literal(literal: &str) -> Option<Decimal> {
+    NumberParts::split(literal, false)?.decimal().ok()
+}
+
+/// A step of the path in a JSONPath filter
+enum Step<'p> {
+    /// `.key`, `['key']` or `["key"]`
+    Key(&'p str),
+    /// `[index]`
+    Index(usize),
+}
+
+/// Read the step at the start of the path in a filter, returning it along with its length, or
+/// `None` if there is none
+fn relative_step(path: &str) -> Option<(Step<'_>, usize)> {
+    if let Some(rest) = path.strip_prefix('.') {
+        let len = rest
+            .find(|chr: char| ".[=!<>".contains(chr) || chr.is_whitespace())
+            .unwrap_or(rest.len());
+        return (len > 0).then(|| (Step::Key(&rest[..len]), len + 1));
+    }
+    let rest = path.strip_prefix('[')?;
+    if let Some(quote @ ('"' | '\'')) = rest.chars().next() {
+        let len = rest[1..].find(quote)?;
+        return rest[len + 2..]
+            .starts_with(']')
+            .then(|| (Step::Key(&rest[1..len + 1]), len + 4));
+    }
+    let len = rest.find(']')?;
+    let index = &rest[..len];
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((Step::Index(index.parse().ok()?), len + 2))
+}
+
+/// What a segment of a JSONPath query selects from the children of a value
+#[derive(Copy, Clone)]
+enum Selector<'p> {
+    /// `.name`, `['name']` or `["name"]`
+    Name(&'p str),
+    /// `.*` or `[*]`
+    Wildcard,
+    /// `[index]`, which counts from the end of the array if it is negative
+    Index(i64),
+    /// `[start:end:step]`, with the step always positive
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: u64,
+    },
+    /// `[?(expression)]`
+    Filter(Filter<'p>),
+}
+
+impl Selector<'_> {
+    /// Whether the selector needs to know the length of an array
+    fn counts_from_end(&self) -> bool {
+        match *self {
+            Selector::Index(index) => index < 0,
+            Selector::Slice { start, end, .. } => {
+                start.is_some_and(|start| start < 0) || end.is_some_and(|end| end < 0)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the selector picks the child of an object with this `key`, or the child of an
+    /// array of length `len` at `index` if there is no key
+    fn selects(
+        &self,
+        key: Option<ObjectKey<'_>>,
+        index: usize,
+        len: usize,
+        child: JSONValue<'_>,
+    ) -> Result<bool, JSONParsingError> {
+        // The position in an array of length `len` of a possibly negative index, or `None` if it
+        // is before the start
+        let position = |index: i64| match usize::try_from(index) {
+            Ok(index) => Some(index),
+            Err(_) => usize::try_from(index.unsigned_abs())
+                .ok()
+                .and_then(|back| len.checked_sub(back)),
+        };
+        let selected = match (*self, key) {
+            (Selector::Name(name), Some(key)) => key.matches(name.chars())?,
+            (Selector::Wildcard, _) => true,
+            (Selector::Index(target), None) => position(target) == Some(index),
+            (Selector::Slice { start, end, step }, None) => {
+                let clamped = |index| position(index).unwrap_or(0);
+                let start = start.map_or(0, clamped);
+                let end = end.map_or(usize::MAX, clamped);
+                (start..end).contains(&index) && ((index - start) as u64).is_multiple_of(step)
+            }
+            (Selector::Filter(filter), _) => filter.test(child)?,
+            _ => false,
+        };
+        Ok(selected)
+    }
+}
+
+/// A segment of a JSONPath query
+#[derive(Copy, Clone)]
+struct Segment<'p> {
+    selector: Selector<'p>,
+    /// Whether the selector applies to all descendants (`..`), rather than just the children
+    descendants: bool,
+    /// The position in the path of the next segment
+    next: usize,
+}
+
+impl<'p> Segment<'p> {
+    /// Read the segment starting at byte `start` of `path`, or return the position at which it is
+    /// malformed
+    fn parse(path: &'p str, start: usize) -> Result<Self, usize> {
+        let rest = &path[start..];
+        let (descendants, position) = if rest.starts_with("..") {
+            (true, start + 2)
+        } else if rest.starts_with('.') || rest.starts_with('[') {
+            (false, start + rest.starts_with('.') as usize)
+        } else {
+            return Err(start);
+        };
+        let rest = &path[position..];
+        let segment = |selector, len| Segment {
+            selector,
+            descendants,
+            next: position + len,
+        };
+        let dotted = position > start;
+        if dotted && rest.starts_with('*') {
+            return Ok(segment(Selector::Wildcard, 1));
+        } else if !rest.starts_with('[') || (dotted && !descendants) {
+            // A name after a dot, which runs up to the next segment
+            let len = rest.find(['.', '[']).unwrap_or(rest.len());
+            if len == 0 {
+                return Err(position);
+            }
+            return Ok(segment(Selector::Name(&rest[..len]), len));
+        }
+        let inner = &rest[1..];
+        if let Some(quote @ ('"' | '\'')) = inner.chars().next() {
+            let len = inner[1..].find(quote).ok_or(position)?;
+            if !inner[len + 2..].starts_with(']') {
+                return Err(position);
+            }
+            return Ok(segment(Selector::Name(&inner[1..len + 1]), len + 4));
+        }
+        if let Some(expression) = inner.strip_prefix("?(") {
+            // The expression ends at the first `)]` outside a string
+            let mut quote = None;
+            let mut chars = expression.char_indices().peekable();
+            while let Some((index, chr)) = chars.next() {
+                match (quote, chr) {
+                    (None, '"' | '\'') => quote = Some(chr),
+                    (Some(open), _) if open == chr => quote = None,
+                    (None, ')') if chars.peek().map(|&(_, chr)| chr) == Some(']') => {
+                        let filter = Filter::parse(&expression[..index])
+                            .map_err(|error| position + 3 + error)?;
+                        return Ok(segment(Selector::Filter(filter), index + 5));
+                    }
+                    _ => {}
+                }
+            }
+            return Err(position);
+        }
+        let len = inner.find(']').ok_or(position)?;
+        let integer = |text: &str| -> Result<Option<i64>, usize> {
+            match text {
+                "" => Ok(None),
+                _ if text.starts_with('+') => Err(position),
+                _ => text.parse().map(Some).map_err(|_| position),
+            }
+        };
+        let mut parts = inner[..len].split(':');
+        let first = parts.next().unwrap_or_default();
+        let selector = match (parts.next(), parts.next(), parts.next()) {
+            (None, _, _) if first == "*" => Selector::Wildcard,
+            (None, _, _) => Selector::Index(integer(first)?.ok_or(position)?),
+            (Some(end), step, None) => Selector::Slice {
+                start: integer(first)?,
+                end: integer(end)?,
+                step: match step.map(integer).transpose()?.flatten() {
+                    None => 1,
+                    Some(step) => u64::try_from(step)
+                        .ok()
+                        .filter(|&step| step > 0)
+                        .ok_or(position)?,
+                },
+            },
+            _ => return Err(position),
+        };
+        Ok(segment(selector, len + 2))
+    }
+}
+
+/// An array or object that a query is part way through
+///
+/// Frames are kept small, as a query holds [`JSONPathIterator::MAX_DEPTH`] of them.  Rather than
+/// iterators and segments, they hold offsets into the contents of the root value and the path,
+/// which [`JSONPathIterator::new`] checks will fit in a `u32`.
+struct Frame {
+    /// The byte offsets in the contents of the root value of the remaining children, and of the
+    /// end of the array or object
+    start: u32,
+    end: u32,
+    /// The depth of the children, relative to the root value
+    depth: u32,
+    /// The position in the path of the segment that is selecting from the children
+    segment: u32,
+    /// The index of the next child
+    index: u32,
+    /// The length of the array, only if the segment needs to count from its end
+    len: u32,
+    object: bool,
+}
+
+impl Frame {
+    /// Start applying `segment`, which is at `position` in the path, to the children of `value`
+    /// in the query from `root`, if it has any
+    fn new(
+        root: JSONValue<'_>,
+        value: JSONValue<'_>,
+        segment: &Segment<'_>,
+        position: usize,
+    ) -> Option<Self> {
+        let (object, len) = match value.value_type {
+            JSONValueType::Array if segment.selector.counts_from_end() => {
+                (false, value.iter_array().ok()?.count())
+            }
+            JSONValueType::Array => (false, 0),
+            JSONValueType::Object => (true, 0),
+            _ => return None,
+        };
+        let start = value.offset + 1 - root.offset;
+        Some(Frame {
+            start: start as u32,
+            end: (start + value.inner().len()) as u32,
+            depth: (value.depth + 1 - root.depth) as u32,
+            segment: position as u32,
+            index: 0,
+            len: len as u32,
+            object,
+        })
+    }
+
+    /// The next child, along with its key if this is an object
+    fn next_child<'a>(
+        &mut self,
+        root: JSONValue<'a>,
+    ) -> Option<Result<(Option<ObjectKey<'a>>, JSONValue<'a>), JSONParsingError>> {
+        let contents = root.contents.get(self.start as usize..self.end as usize)?;
+        let offset = root.offset + self.start as usize;
+        let depth = root.depth + self.depth as usize;
+        let options = root.options;
+        let (child, offset) = if self.object {
+            let mut entries = JSONObjectIterator {
+                contents,
+                offset,
+                depth,
+                options,
+            };
+            let entry = entries.next_entry()?;
+            (entry.map(|(key, value)| (Some(key), value)), entries.offset)
+        } else {
+            let mut items = JSONArrayIterator {
+                contents,
+                offset,
+                depth,
+                options,
+            };
+            (Ok((None, items.next()?)), items.offset)
+        };
+        self.start = (offset - root.offset) as u32;
+        self.index += 1;
+        Some(child)
+    }
+}
+
+/// An iterator over the values selected by a JSONPath query
+///
+/// Constructed with [`JSONValue::query`].  The items are `Result<JSONValue, JSONParsingError>`,
+/// in the order that they appear in the payload, except that with recursive descent a value is
+/// found before its descendants.  Like [`JSONArrayIterator`], a malformed array simply ends,
+/// whereas a malformed object is reported as an error.
+pub struct JSONPathIterator<'a, 'p> {
+    path: &'p str,
+    /// The value the query starts from
+    root: JSONValue<'a>,
+    /// Whether the root value has been selected from yet
+    started: bool,
+    stack: [Option<Frame>; JSONPathIterator::MAX_DEPTH],
+    /// The number of frames in use
+    depth: usize,
+    /// An error to report once the value that caused it has been dealt with
+    error: Option<JSONParsingError>,
+}
+
+impl<'a, 'p> JSONPathIterator<'a, 'p> {
+    /// The number of arrays and objects a query can be part way through at once, which is the
+    /// number of segments in the path, plus the depth of nesting searched with `..`
+    pub const MAX_DEPTH: usize = 32;
+
+    /// Check that `path` is a valid query, and start it from `root`
+    ///
+    /// A `root` or `path` too long for offsets in it to fit in a `u32` gives an error of kind
+    /// [`JSONParsingErrorKind::ValueTooLong`].
+    pub(crate) fn new(root: JSONValue<'a>, path: &'p str) -> Result<Self, JSONParsingError> {
+        if u32::try_from(root.contents.len().max(path.len())).is_err() {
+            return Err(JSONParsingErrorKind::ValueTooLong.at(root.offset));
+        }
+        let error = |position| JSONParsingErrorKind::InvalidPath(position).at(root.offset);
+        let mut position = match path.strip_prefix('$') {
+            Some(_) => 1,
+            None => return Err(error(0)),
+        };
+        while position < path.len() {
+            position = Segment::parse(path, position).map_err(error)?.next;
+        }
+        Ok(JSONPathIterator {
+            path,
+            root,
+            started: false,
+            stack: [(); JSONPathIterator::MAX_DEPTH].map(|_| None),
+            depth: 0,
+            error: None,
+        })
+    }
+
+    /// Read the segment at `position` in the path, reporting a malformed one at `offset`
+    fn segment(&self, position: usize, offset: usize) -> Result<Segment<'p>, JSONParsingError> {
+        Segment::parse(self.path, position)
+            .map_err(|position| JSONParsingErrorKind::InvalidPath(position).at(offset))
+    }
+
+    /// Start `frame`, unless the stack is full
+    fn push(&mut self, frame: Frame, value: JSONValue<'a>) -> Result<(), JSONParsingError> {
+        let slot = self
+            .stack
+            .get_mut(self.depth)
+            .ok_or_else(|| JSONParsingErrorKind::DepthLimitExceeded.at(value.offset))?;
+        *slot = Some(frame);
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Continue the query from a `value` that has been selected, where `position` is that of the
+    /// next segment in the path, returning the value if the path is finished
+    fn select(
+        &mut self,
+        value: JSONValue<'a>,
+        position: usize,
+    ) -> Option<Result<JSONValue<'a>, JSONParsingError>> {
+        if position >= self.path.len() {
+            return Some(Ok(value));
+        }
+        let segment = match self.segment(position, value.offset) {
+            Ok(segment) => segment,
+            Err(error) => return Some(Err(error)),
+        };
+        let frame = Frame::new(self.root, value, &segment, position)?;
+        self.push(frame, value).err().map(Err)
+    }
+}
+
+impl<'a, 'p> Iterator for JSONPathIterator<'a, 'p> {
+    type Item = Result<JSONValue<'a>, JSONParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            if let Some(result) = self.select(self.root, 1) {
+                return Some(result);
+            }
+        }
+        loop {
+            if let Some(error) = self.error.take() {
+                return Some(Err(error));
+            }
+            let root = self.root;
+            let frame = self.stack.get_mut(self.depth.checked_sub(1)?)?.as_mut()?;
+            let (index, len) = (frame.index as usize, frame.len as usize);
+            let position = frame.segment as usize;
+            let (key, child) = match frame.next_child(root) {
+                Some(Ok(entry)) => entry,
+                end => {
+                    // The object iterator does not continue after an error
+                    self.stack[self.depth - 1] = None;
+                    self.depth -= 1;
+                    match end {
+                        Some(Err(error)) => return Some(Err(error)),
+                        _ => continue,
+                    }
+                }
+            };
+            let segment = match self.segment(position, child.offset) {
+                Ok(segment) => segment,
+                Err(error) => return Some(Err(error)),
+            };
+            // Search the descendants after anything that follows from the child itself
+            if segment.descendants {
+                if let Some(frame) = Frame::new(root, child, &segment, position) {
+                    self.error = self.push(frame, child).err();
+                }
+            }
+            match segment.selector.selects(key, index, len, child) {
+                Ok(true) => {
+                    if let Some(result) = self.select(child, segment.next) {
+                        return Some(result);
+                    }
+                }
+                Ok(false) => {}
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
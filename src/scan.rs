@@ -0,0 +1,495 @@
+//! Finding the extent of JSON values in a payload
+//!
+//! Scanning is not recursive, so that arbitrarily deeply nested input cannot exhaust the stack.
+
+use crate::{EscapedStringIterator, JSONParsingError, JSONParsingErrorKind, ParseOptions};
+
+/// Whitespace allowed by JSON5, which is that of JavaScript
+fn is_json5_whitespace(chr: char) -> bool {
+    (chr.is_whitespace() && chr != '\u{85}') || chr == '\u{feff}'
+}
+
+/// The length of the whitespace, and any comments if enabled in `options`, at the start of `value`
+///
+/// If `strict` is true, only the whitespace allowed by RFC 8259 (or by JSON5, if enabled in
+/// `options`) is skipped. Otherwise, any Unicode whitespace is.
+///
+/// `offset` is the position of `value` in the original input, and is used to locate errors.
+pub(crate) fn whitespace_len(
+    value: &str,
+    offset: usize,
+    options: ParseOptions,
+    strict: bool,
+) -> Result<usize, JSONParsingError> {
+    let is_whitespace: fn(char) -> bool = match (strict, options.json5_enabled()) {
+        (true, false) => |chr| matches!(chr, ' ' | '\t' | '\n' | '\r'),
+        (true, true) => is_json5_whitespace,
+        // NOTE(robert): This trims from the "start" which may be different for RTL languages.  What
+        // do we do for JSON?
+        (false, false) => char::is_whitespace,
+        (false, true) => |chr| chr.is_whitespace() || chr == '\u{feff}',
+    };
+    let mut position = 0;
+    loop {
+        let rest = value[position..].trim_start_matches(is_whitespace);
+        position = value.len() - rest.len();
+        if !options.comments_enabled() {
+            return Ok(position);
+        }
+        if let Some(comment) = rest.strip_prefix("//") {
+            position += 2 + comment
+                .find(['\n', '\r', '\u{2028}', '\u{2029}'])
+                .unwrap_or(comment.len());
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            match comment.find("*/") {
+                Some(end) => position += 2 + end + 2,
+                None => return Err(JSONParsingErrorKind::EndOfStream.at(offset + value.len())),
+            }
+        } else {
+            return Ok(position);
+        }
+    }
+}
+
+/// The length of the JSON5 unquoted key at the start of `contents`, or zero if there is none
+///
+/// Keys follow the rules for JavaScript identifiers, except that escape sequences are not
+/// supported.
+pub(crate) fn identifier_len(contents: &str) -> usize {
+    let mut chars = contents.char_indices();
+    match chars.next() {
+        Some((_, chr)) if chr.is_alphabetic() || chr == '$' || chr == '_' => {}
+        _ => return 0,
+    }
+    chars
+        .find(|&(_, chr)| {
+            !(chr.is_alphanumeric() || matches!(chr, '$' | '_' | '\u{200c}' | '\u{200d}'))
+        })
+        .map_or(contents.len(), |(index, _)| index)
+}
+
+/// The length of `literal` if `contents` starts with it
+///
+/// `offset` is the position of `contents` in the original input, and is used to locate errors.
+fn literal_len(contents: &str, literal: &str, offset: usize) -> Result<usize, JSONParsingError> {
+    if contents.starts_with(literal) {
+        Ok(literal.len())
+    } else if literal.starts_with(contents) {
+        Err(JSONParsingErrorKind::EndOfStream.at(offset + contents.len()))
+    } else {
+        Err(JSONParsingErrorKind::UnexpectedToken.at(offset))
+    }
+}
+
+/// The length of the string at the start of `contents`, checking it against RFC 8259 (or JSON5,
+/// if enabled in `options`)
+///
+/// `offset` is the position of `contents` in the original input, and is used to locate errors.
+fn strict_string_len(
+    contents: &str,
+    offset: usize,
+    options: ParseOptions,
+) -> Result<usize, JSONParsingError> {
+    // Decode the string exactly as `JSONValue::iter_string` would, so that a verified string can
+    // always be read
+    let quote = contents.chars().next().unwrap_or('"');
+    let mut chars = EscapedStringIterator::new(&contents[1..], offset + 1, quote, options);
+    loop {
+        // Control characters must be escaped, although JSON5 only insists on this for line breaks
+        match chars.peek_raw() {
+            Some('\n' | '\r') => {}
+            Some('\u{0}'..='\u{1f}') if !options.json5_enabled() => {}
+            _ => match chars.read_char() {
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e),
+                None => return Ok(chars.offset() - offset),
+            },
+        }
+        return Err(JSONParsingErrorKind::UnexpectedToken.at(chars.offset()));
+    }
+}
+
+/// The length of the number at the start of `contents`, checking it against RFC 8259 (or JSON5,
+/// if `json5` is true)
+///
+/// `offset` is the position of `contents` in the original input, and is used to locate errors.
+pub(crate) fn strict_number_len(
+    contents: &str,
+    offset: usize,
+    json5: bool,
+) -> Result<usize, JSONParsingError> {
+    let bytes = contents.as_bytes();
+    let count = |index: usize, is_digit: fn(&u8) -> bool| {
+        bytes[index..].iter().take_while(|b| is_digit(b)).count()
+    };
+    let digits = |index: usize| count(index, u8::is_ascii_digit);
+    let missing_digit = |index: usize| {
+        if index == bytes.len() {
+            JSONParsingErrorKind::EndOfStream.at(offset + index)
+        } else {
+            JSONParsingErrorKind::UnexpectedToken.at(offset + index)
+        }
+    };
+
+    // The number must not run straight into another character that could be part of it
+    let end = |index: usize| match bytes.get(index) {
+        Some(b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-') => {
+            Err(JSONParsingErrorKind::UnexpectedToken.at(offset + index))
+        }
+        Some(b) if json5 && (b.is_ascii_alphanumeric() || matches!(b, b'$' | b'_')) => {
+            Err(JSONParsingErrorKind::UnexpectedToken.at(offset + index))
+        }
+        _ => Ok(index),
+    };
+
+    let mut index = 0;
+    match bytes.first() {
+        Some(b'-') => index += 1,
+        Some(b'+') if json5 => index += 1,
+        _ => {}
+    }
+    let integer_digits = match bytes.get(index) {
+        Some(b'I') if json5 => {
+            return end(index + literal_len(&contents[index..], "Infinity", offset + index)?)
+        }
+        Some(b'N') if json5 => {
+            return end(index + literal_len(&contents[index..], "NaN", offset + index)?)
+        }
+        Some(b'0') if json5 && matches!(bytes.get(index + 1), Some(b'x' | b'X')) => {
+            return match count(index + 2, u8::is_ascii_hexdigit) {
+                0 => Err(missing_digit(index + 2)),
+                n => end(index + 2 + n),
+            };
+        }
+        // No leading zeros are allowed
+        Some(b'0') => 1,
+        Some(b'1'..=b'9') => digits(index),
+        // JSON5 allows the integer part to be left out
+        Some(b'.') if json5 => 0,
+        _ => return Err(missing_digit(index)),
+    };
+    index += integer_digits;
+    if bytes.get(index) == Some(&b'.') {
+        index += 1;
+        match digits(index) {
+            // JSON5 also allows the fractional part to be left out, but not both
+            0 if json5 && integer_digits > 0 => {}
+            0 => return Err(missing_digit(index)),
+            n => index += n,
+        }
+    }
+    if matches!(bytes.get(index), Some(b'e' | b'E')) {
+        index += 1;
+        if matches!(bytes.get(index), Some(b'+' | b'-')) {
+            index += 1;
+        }
+        match digits(index) {
+            0 => return Err(missing_digit(index)),
+            n => index += n,
+        }
+    }
+    end(index)
+}
+
+/// The length of the string at the start of `contents`, only looking for the closing quote
+///
+/// `offset` is the position of `contents` in the original input, and is used to locate errors.
+fn lenient_string_len(contents: &str, offset: usize) -> Result<usize, JSONParsingError> {
+    let quote = contents.chars().next().unwrap_or('"');
+    let mut is_escaped = false;
+    for (index, chr) in contents.char_indices().skip(1) {
+        if chr == quote && !is_escaped {
+            return Ok(index + 1);
+        } else if chr == '\\' {
+            is_escaped = !is_escaped;
+        } else {
+            is_escaped = false;
+        }
+    }
+    Err(JSONParsingErrorKind::EndOfStream.at(offset + contents.len()))
+}
+
+/// The length of the run of characters that could make up a number at the start of `contents`
+///
+/// If `json5` is true, this includes the letters of hexadecimal numbers, `Infinity` and `NaN`.
+fn lenient_number_len(contents: &str, json5: bool) -> usize {
+    contents
+        .bytes()
+        .take_while(|b| {
+            matches!(b, b'0'..=b'9' | b'-' | b'+' | b'e' | b'E' | b'.')
+                || (json5 && b.is_ascii_alphanumeric())
+        })
+        .count()
+}
+
+/// The length of the string, number or literal at the start of `contents`
+///
+/// `offset` is the position of `contents` in the original input, and is used to locate errors.
+fn scalar_len(
+    contents: &str,
+    offset: usize,
+    options: ParseOptions,
+    strict: bool,
+) -> Result<usize, JSONParsingError> {
+    let json5 = options.json5_enabled();
+    let string_len = || {
+        if strict {
+            strict_string_len(contents, offset, options)
+        } else {
+            lenient_string_len(contents, offset)
+        }
+    };
+    let number_len = || {
+        if strict {
+            strict_number_len(contents, offset, json5)
+        } else {
+            Ok(lenient_number_len(contents, json5))
+        }
+    };
+    match contents.chars().next() {
+        Some('"') => string_len(),
+        Some('\'') if json5 => string_len(),
+        Some('0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | '-') => number_len(),
+        Some('+' | '.' | 'I' | 'N') if json5 => number_len(),
+        Some('t') => literal_len(contents, "true", offset),
+        Some('f') => literal_len(contents, "false", offset),
+        Some('n') => literal_len(contents, "null", offset),
+        None => Err(JSONParsingErrorKind::EndOfStream.at(offset)),
+        _ => Err(JSONParsingErrorKind::UnexpectedToken.at(offset)),
+    }
+}
+
+/// A stack of bits recording whether each open container is an object or an array
+struct ContainerStack {
+    bits: [u32; ParseOptions::MAX_DEPTH / 32],
+    len: usize,
+}
+
+impl ContainerStack {
+    fn new() -> Self {
+        ContainerStack {
+            bits: [0; ParseOptions::MAX_DEPTH / 32],
+            len: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == ParseOptions::MAX_DEPTH
+    }
+
+    fn push(&mut self, is_object: bool) {
+        let (word, bit) = (self.len / 32, self.len % 32);
+        if is_object {
+            self.bits[word] |= 1 << bit;
+        } else {
+            self.bits[word] &= !(1 << bit);
+        }
+        self.len += 1;
+    }
+
+    fn pop(&mut self) {
+        self.len -= 1;
+    }
+
+    fn top_is_object(&self) -> bool {
+        match self.len.checked_sub(1) {
+            Some(top) => self.bits[top / 32] & (1 << (top % 32)) != 0,
+            None => false,
+        }
+    }
+}
+
+/// What the scanner expects to see next
+#[derive(Copy, Clone)]
+enum Expect {
+    Value,
+    /// A value, or the end of an empty (or, if not strict or if JSON5, trailing comma terminated)
+    /// array
+    ValueOrEnd,
+    Key,
+    /// A key, or the end of an empty (or, if not strict or if JSON5, trailing comma terminated)
+    /// object
+    KeyOrEnd,
+    Colon,
+    CommaOrEnd,
+}
+
+/// The length of the value at the start of `contents`, which must already be trimmed
+///
+/// `offset` is the position of `contents` in the original input, and is used to locate errors.
+/// `depth` is the number of containers enclosing the value, which counts towards the nesting
+/// limit in `options`.
+///
+/// If `strict` is false, this only scans far enough to find the end of the value, and so may
+/// accept malformed values. If `strict` is true, the value must conform to RFC 8259, or to JSON5 if
+/// it is enabled in `options`.
+pub(crate) fn value_len(
+    contents: &str,
+    offset: usize,
+    depth: usize,
+    options: ParseOptions,
+    strict: bool,
+) -> Result<usize, JSONParsingError> {
+    let json5 = options.json5_enabled();
+    let mut containers = ContainerStack::new();
+    let mut expect = Expect::Value;
+    let mut position = 0;
+    loop {
+        position += whitespace_len(&contents[position..], offset + position, options, strict)?;
+        let rest = &contents[position..];
+        let chr = match rest.chars().next() {
+            Some(chr) => chr,
+            None => return Err(JSONParsingErrorKind::EndOfStream.at(offset + position)),
+        };
+        match (expect, chr) {
+            (Expect::ValueOrEnd, ']')
+            | (Expect::KeyOrEnd, '}')
+            | (Expect::CommaOrEnd, ']' | '}') => {
+                if (chr == '}') != containers.top_is_object() {
+                    return Err(JSONParsingErrorKind::UnexpectedToken.at(offset + position));
+                }
+                containers.pop();
+                position += 1;
+                if containers.is_empty() {
+                    return Ok(position);
+                }
+                expect = Expect::CommaOrEnd;
+            }
+            (Expect::CommaOrEnd, ',') => {
+                position += 1;
+                expect = match (containers.top_is_object(), strict && !json5) {
+                    (true, true) => Expect::Key,
+                    (true, false) => Expect::KeyOrEnd,
+                    (false, true) => Expect::Value,
+                    (false, false) => Expect::ValueOrEnd,
+                };
+            }
+            (Expect::Colon, ':') => {
+                position += 1;
+                expect = Expect::Value;
+            }
+            (Expect::Value | Expect::ValueOrEnd, '{' | '[') => {
+                if depth + containers.len >= options.max_depth() || containers.is_full() {
+                    return Err(JSONParsingErrorKind::DepthLimitExceeded.at(offset + position));
+                }
+                containers.push(chr == '{');
+                position += 1;
+                expect = if chr == '{' {
+                    Expect::KeyOrEnd
+                } else {
+                    Expect::ValueOrEnd
+                };
+            }
+            (Expect::Key | Expect::KeyOrEnd, _) if json5 && identifier_len(rest) > 0 => {
+                position += identifier_len(rest);
+                expect = Expect::Colon;
+            }
+            (Expect::Value | Expect::ValueOrEnd, _)
+            | (Expect::Key | Expect::KeyOrEnd, '"')
+            | (Expect::Key | Expect::KeyOrEnd, '\'') => {
+                position += scalar_len(rest, offset + position, options, strict)?;
+                if containers.is_empty() {
+                    return Ok(position);
+                }
+                expect = if matches!(expect, Expect::Key | Expect::KeyOrEnd) {
+                    Expect::Colon
+                } else {
+                    Expect::CommaOrEnd
+                };
+            }
+            (Expect::Key | Expect::KeyOrEnd, '{' | '[' | '0'..='9' | '-' | 't' | 'f' | 'n') => {
+                // Keys must be strings
+                return Err(JSONParsingErrorKind::CannotParseString.at(offset + position));
+            }
+            _ => {
+                return Err(JSONParsingErrorKind::UnexpectedToken.at(offset + position));
+            }
+        }
+    }
+}
+
+/// The position of the comma or closing bracket that ends the array item or object entry at the
+/// start of `contents`, or `None` if the input ends first
+///
+/// This only matches brackets and skips over strings and comments, without checking anything
+/// else, so is much faster than finding the length of the item with [`value_len`].
+fn item_end(contents: &str, options: ParseOptions) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut position = 0;
+    while let Some(&byte) = contents.as_bytes().get(position) {
+        match byte {
+            b'"' => position += lenient_string_len(&contents[position..], 0).ok()? - 1,
+            b'\'' if options.json5_enabled() => {
+                position += lenient_string_len(&contents[position..], 0).ok()? - 1
+            }
+            b'/' if options.comments_enabled() => {
+                let comment = whitespace_len(&contents[position..], 0, options, false).ok()?;
+                position += comment.saturating_sub(1);
+            }
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' | b',' if depth == 0 => return Some(position),
+            b']' | b'}' => depth -= 1,
+            _ => {}
+        }
+        position += 1;
+    }
+    None
+}
+
+/// An iterator over the positions of the items in an array, or entries in an object, that find
+/// them by matching brackets and quotes as [`item_end`] does
+pub(crate) struct ItemStarts<'a> {
+    /// The contents of the array or object after the opening bracket, or the comma after an item
+    contents: &'a str,
+    /// Byte offset of `contents` in the original input
+    offset: usize,
+    options: ParseOptions,
+    position: usize,
+    done: bool,
+}
+
+impl<'a> ItemStarts<'a> {
+    pub(crate) fn new(contents: &'a str, offset: usize, options: ParseOptions) -> Self {
+        ItemStarts {
+            contents,
+            offset,
+            options,
+            position: 0,
+            done: false,
+        }
+    }
+
+    /// Find the start of the next item, if there is one, and move past it
+    fn advance(&mut self) -> Result<Option<usize>, JSONParsingError> {
+        let rest = &self.contents[self.position..];
+        let start =
+            self.position + whitespace_len(rest, self.offset + self.position, self.options, false)?;
+        let rest = &self.contents[start..];
+        if rest.starts_with([']', '}']) {
+            return Ok(None);
+        }
+        let end = item_end(rest, self.options).ok_or_else(|| {
+            JSONParsingErrorKind::EndOfStream.at(self.offset + self.contents.len())
+        })?;
+        // Stop at the closing bracket after the last item
+        self.position = start + end + rest[end..].starts_with(',') as usize;
+        Ok(Some(start))
+    }
+}
+
+impl Iterator for ItemStarts<'_> {
+    type Item = Result<usize, JSONParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let start = self.advance().transpose();
+        self.done = !matches!(start, Some(Ok(_)));
+        start
+    }
+}
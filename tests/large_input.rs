@@ -33,6 +33,25 @@ fn large_input_read() {
     );
 }
 
+#[test]
+fn large_input_pointer() {
+    let value = JSONValue::load(JSON_PAYLOAD);
+    assert_eq!(
+        value.pointer("/7/_id").and_then(|id| id.read_string()),
+        Ok("6136737d845794b21eddadc0")
+    );
+    assert_eq!(
+        value
+            .pointer("/7/friends/2/name")
+            .and_then(|name| name.read_string()),
+        Ok("Rosario Curtis")
+    );
+    assert_eq!(
+        value.pointer("/7/friends/3/name").unwrap_err().kind(),
+        JSONParsingErrorKind::PathNotFound(2)
+    );
+}
+
 const JSON_PAYLOAD : &str = "[
   {
     \"_id\": \"6136737d155df9328fdae6fa\",
@@ -0,0 +1,420 @@
+//! Check that no input can make the public API panic
+//!
+//! There is no fuzzer in the dev-dependencies, so this generates inputs with a small deterministic
+//! pseudo-random number generator: truncations and mutations of valid documents, and random
+//! strings drawn from characters that are meaningful to JSON.
+//!
+//! Values that verify are also checked to read back without errors.
+
+use microjson::*;
+
+const SEEDS: [&str; 11] = [
+    r#"{"id": 0, "name": "Ginger Fuller", "tags": ["a", "b"], "ok": true}"#,
+    r#"[1, -2.5e+3, 0.25E-2, null, false, true, "£\n\"\\"]"#,
+    r#"{"a": {"b": [[], {}, [{}], {"c": null}]}}"#,
+    r#" "😀 ⟼ \/ \b\f\r\t" "#,
+    r##"[{"a":{"email":"d@"},"m":"#20\n\n.\n"}]"##,
+    "{\"£\": \"⟼\", \"k\": [\"ü\", 1e5]}",
+    "  -0.0  ",
+    "[\"tr\", tru, fals, nul]",
+    r#"{"\ud83d\ude00": ["\ud834\udd1e", "\u00e9\u0000", "\udbff\udfff"]}"#,
+    "// c\n{\"a\": /* b */ [1, 2 /**/]} // d",
+    "{a: 'b\\'\\x41\\\n', /* c */ $d: [+.5, 0xFf, -Infinity, NaN,], // e\n}",
+];
+
+const ALPHABET: [char; 35] = [
+    '{', '}', '[', ']', '"', '\'', ':', ',', '\\', '/', '*', 'u', 'x', 't', 'f', 'n', 'e', 'E',
+    '.', '-', '+', '0', '1', '8', '9', 'a', 'd', ' ', '\n', '\r', '\t', '\u{1}', '£', '\u{a0}',
+    '😀',
+];
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> usize {
+        // xorshift64*
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) as usize
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.next() % n
+    }
+}
+
+/// Check that every escape sequence in the string at the start of `value` can be decoded
+fn assert_decodes(value: JSONValue, input: &str) {
+    for chr in value.iter_string().unwrap() {
+        if let Err(e) = chr {
+            panic!("{:?} verified, but reading it failed with {}", input, e);
+        }
+    }
+}
+
+/// Quote an object key so that it can be loaded as a string with `options`
+fn quote_key(key: &str, options: ParseOptions) -> String {
+    #[cfg(feature = "json5")]
+    {
+        // The key may have come from a single quoted string
+        if options.json5() && key.contains('"') {
+            return format!("'{}'", key);
+        }
+    }
+    let _ = options;
+    format!("\"{}\"", key)
+}
+
+/// Call every accessor on `value`, and recurse into its children
+///
+/// `verified` holds the options used if `value` is part of a payload that passed verification.
+fn exercise(value: JSONValue, input: &str, depth: usize, verified: Option<ParseOptions>) {
+    let _ = value.verify();
+    let _ = value.is_null();
+    let _ = value.read_boolean();
+    let _ = value.read_integer();
+    #[cfg(feature = "float")]
+    let _ = value.read_float();
+    let _ = value.read_number::<u8>();
+    let _ = value.read_number::<i128>();
+    #[cfg(feature = "float")]
+    let _ = value.read_number::<f64>();
+    let _ = value.number_token();
+    let _ = value.is_integer_literal();
+    let _ = value.has_fraction();
+    let _ = value.has_exponent();
+    let _ = value.is_negative();
+    let _ = value.fits_i64();
+    let _ = value.fits_u64();
+    let _ = value.read_scaled(3);
+    let _ = value.read_fixed::<16>();
+    let _ = value.read_fixed::<63>();
+    if let Ok(decimal) = value.read_decimal() {
+        let _ = decimal.to_fixed(0);
+        let _ = decimal.to_fixed(u32::MAX);
+    }
+    let _ = value.read_string();
+    let mut buffer = [0; 8];
+    if let Err(e) = value.read_string_into(&mut buffer) {
+        // Decoding must succeed once the buffer is large enough
+        if let JSONParsingErrorKind::BufferTooSmall(needed) = e.kind() {
+            assert!(value.read_string_into(&mut vec![0; needed]).is_ok());
+        }
+    }
+    let _ = value.string_eq("a");
+    #[cfg(feature = "alloc")]
+    if let Ok(decoded) = value.read_string_cow() {
+        assert!(value.string_eq(&decoded).unwrap());
+    }
+    if let (Some(_), Ok(chars)) = (verified, value.iter_string()) {
+        if let Ok(decoded) = chars.collect::<Result<String, _>>() {
+            assert_eq!(value.string_eq(&decoded), Ok(true), "{:?}", input);
+        }
+    }
+    if let Ok(chars) = value.iter_string() {
+        for chr in chars {
+            if let Err(e) = chr {
+                assert!(
+                    verified.is_none(),
+                    "{:?} verified, but reading it failed with {}",
+                    input,
+                    e
+                );
+                let _ = e.line_column(input);
+            }
+        }
+    }
+    let _ = value.get_key_value("a");
+    if let Ok([a, empty]) = value.get_key_values(["a", ""]) {
+        // Without duplicates, this finds the same values as looking up each key alone
+        assert_eq!(a.is_some(), value.get_key_value("a").is_ok());
+        assert_eq!(empty.is_some(), value.get_key_value("").is_ok());
+    }
+    let _ = value.pointer("/a/0");
+    let _ = value.pointer("/0/~1/~0");
+    let _ = value.is_empty();
+    // Size hints must hold even for malformed input
+    if let Ok(items) = value.iter_array() {
+        let (lower, upper) = items.size_hint();
+        let count = items.count();
+        assert!(lower <= count && upper.is_some_and(|upper| upper >= count));
+    }
+    if let Ok(entries) = value.iter_object() {
+        let (lower, upper) = entries.size_hint();
+        let count = entries.count();
+        assert!(lower <= count && upper.is_some_and(|upper| upper >= count));
+    }
+    let _ = value.get_index(3);
+    if let (Some(_), Ok(len)) = (verified, value.len()) {
+        // In a verified payload, skipping items agrees with reading them
+        if let Ok(items) = value.iter_array() {
+            assert_eq!(value.iter_array().unwrap().count(), len);
+            for (index, item) in items.enumerate().take(64) {
+                let found = value.get_index(index).unwrap();
+                assert_eq!(found.value_type, item.value_type);
+            }
+        }
+        if let Ok(entries) = value.iter_object() {
+            assert_eq!(entries.count(), len);
+        }
+    }
+    let mut buffer = [0; 64];
+    if let Ok(index) = value.index_into(&mut buffer) {
+        let _ = index.get_index(3);
+        let _ = index.get_key_value("a");
+        if verified.is_some() {
+            // An index finds the same items as reading through the value
+            if let Ok(items) = value.iter_array() {
+                for (position, item) in items.enumerate() {
+                    let found = index.get_index(position).unwrap();
+                    assert_eq!(found.value_type, item.value_type);
+                }
+            }
+            if let Ok(entries) = value.iter_object() {
+                for entry in entries {
+                    let (key, _) = entry.unwrap();
+                    if !key.contains('\\') {
+                        let found = index.get_key_value(key).unwrap();
+                        let expected = value.get_key_value(key).unwrap();
+                        assert_eq!(found.value_type, expected.value_type);
+                        assert_eq!(found.number_token(), expected.number_token());
+                    }
+                }
+            }
+        }
+    }
+    if depth == 0 {
+        return;
+    }
+    if let Ok(items) = value.iter_array() {
+        for item in items.take(64) {
+            exercise(item, input, depth - 1, verified);
+        }
+    }
+    if let Ok(items) = value.iter_object() {
+        for item in items.take(64) {
+            match item {
+                Ok((key, item)) => {
+                    if let Some(options) = verified {
+                        assert_decodes(options.load(&quote_key(key, options)), input);
+                    }
+                    exercise(item, input, depth - 1, verified)
+                }
+                Err(e) => {
+                    let _ = e.line_column(input);
+                }
+            }
+        }
+    }
+    #[cfg(feature = "alloc")]
+    if let Ok(items) = value.iter_object() {
+        for item in items.decode_keys().take(64) {
+            match item {
+                // Decoded keys must find their entry again
+                Ok((key, _)) => assert!(verified.is_none() || value.get_key_value(&key).is_ok()),
+                Err(e) => assert!(
+                    verified.is_none(),
+                    "{:?} verified, but failed with {}",
+                    input,
+                    e
+                ),
+            }
+        }
+    }
+}
+
+/// Walk `value`, decoding its strings in place, and recurse into its children
+///
+/// Strings in a verified payload must decode just as `iter_string` would decode them.
+fn exercise_mut(value: JSONValueMut, input: &str, depth: usize, verified: Option<ParseOptions>) {
+    let expected: Option<String> = match verified {
+        Some(_) => value.as_value().iter_string().ok().map(|chars| {
+            chars.collect::<Result<_, _>>().unwrap_or_else(|e| {
+                panic!("{:?} verified, but reading it failed with {}", input, e)
+            })
+        }),
+        None => None,
+    };
+    let _ = value.as_value().read_integer();
+    match value.value_type {
+        JSONValueType::String => match value.read_string_in_place() {
+            Ok(decoded) => {
+                if let Some(expected) = expected {
+                    assert_eq!(decoded, expected, "{:?}", input);
+                }
+            }
+            Err(e) => {
+                assert!(
+                    verified.is_none(),
+                    "{:?} verified, but decoding failed with {}",
+                    input,
+                    e
+                );
+                let _ = e.line_column(input);
+            }
+        },
+        _ if depth == 0 => {}
+        JSONValueType::Array => {
+            if let Ok(items) = value.iter_array() {
+                for item in items.take(64) {
+                    exercise_mut(item, input, depth - 1, verified);
+                }
+            }
+        }
+        JSONValueType::Object => {
+            if let Ok(items) = value.iter_object() {
+                for item in items.take(64) {
+                    match item {
+                        Ok((_, item)) => exercise_mut(item, input, depth - 1, verified),
+                        Err(e) => {
+                            assert!(
+                                verified.is_none(),
+                                "{:?} verified, but failed with {}",
+                                input,
+                                e
+                            );
+                            let _ = e.line_column(input);
+                        }
+                    }
+                }
+            }
+        }
+        _ => {
+            let _ = value.get_key_value("a");
+        }
+    }
+}
+
+/// Run queries that search the whole of `value`
+fn exercise_queries(value: JSONValue, input: &str) {
+    for path in [
+        "$..*",
+        "$[*].a[-1:]",
+        "$..[?(@.a)]",
+        r#"$..[?(@[0] >= -1.5e2)]['a'][::2]"#,
+        r#"$.*[?(@ != "£")]"#,
+    ] {
+        for result in value.query(path).unwrap().take(256) {
+            if let Err(e) = result {
+                let _ = e.line_column(input);
+            }
+        }
+    }
+}
+
+fn exercise_input_with(input: &str, options: ParseOptions) {
+    exercise_queries(options.load(input), input);
+    match options.load_and_verify(input) {
+        Ok(value) => exercise(value, input, 8, Some(options)),
+        Err(e) => {
+            let _ = e.line_column(input);
+            exercise(options.load(input), input, 8, None);
+        }
+    }
+    let mut buffer = input.as_bytes().to_vec();
+    if let Ok(value) = options.load_mut(&mut buffer) {
+        let verified = options.load_and_verify(input).ok().map(|_| options);
+        exercise_mut(value, input, 8, verified);
+    }
+    if let Ok((value, rest)) = options.load_prefix(input) {
+        exercise(value, input, 8, Some(options));
+        let _ = options.load_prefix(rest);
+    }
+}
+
+fn exercise_input(input: &str) {
+    exercise_input_with(input, ParseOptions::new());
+    exercise_input_with(
+        input,
+        ParseOptions::new()
+            .with_comments(true)
+            .with_integral_floats(true),
+    );
+    #[cfg(feature = "json5")]
+    exercise_input_with(input, ParseOptions::new().with_json5(true));
+    // Also try every possible type, as the value type is public
+    for value_type in [
+        JSONValueType::String,
+        JSONValueType::Number,
+        JSONValueType::Object,
+        JSONValueType::Array,
+        JSONValueType::Bool,
+        JSONValueType::Null,
+        JSONValueType::Error,
+    ] {
+        let mut value = JSONValue::load(input);
+        value.value_type = value_type;
+        exercise(value, input, 1, None);
+        let mut buffer = input.as_bytes().to_vec();
+        if let Ok(mut value) = JSONValueMut::load(&mut buffer) {
+            value.value_type = value_type;
+            exercise_mut(value, input, 1, None);
+        }
+    }
+}
+
+#[test]
+fn truncations() {
+    for seed in SEEDS {
+        for (index, _) in seed.char_indices() {
+            exercise_input(&seed[..index]);
+            exercise_input(&seed[index..]);
+        }
+    }
+}
+
+#[test]
+fn mutations() {
+    let mut rng = Rng(0x5eed);
+    for _ in 0..4000 {
+        let mut chars: Vec<char> = SEEDS[rng.below(SEEDS.len())].chars().collect();
+        for _ in 0..1 + rng.below(4) {
+            let index = rng.below(chars.len() + 1);
+            let replacement = ALPHABET[rng.below(ALPHABET.len())];
+            match rng.below(3) {
+                0 => chars.insert(index, replacement),
+                1 if index < chars.len() => {
+                    chars.remove(index);
+                }
+                _ if index < chars.len() => chars[index] = replacement,
+                _ => {}
+            }
+        }
+        exercise_input(&chars.into_iter().collect::<String>());
+    }
+}
+
+#[test]
+fn random_strings() {
+    let mut rng = Rng(0xdecaf);
+    for _ in 0..4000 {
+        let length = rng.below(24);
+        let input: String = (0..length)
+            .map(|_| ALPHABET[rng.below(ALPHABET.len())])
+            .collect();
+        exercise_input(&input);
+    }
+}
+
+#[test]
+fn deep_nesting_on_a_small_stack() {
+    // Scanning is not recursive, so even absurdly deep input fits on a small stack
+    std::thread::Builder::new()
+        .stack_size(64 * 1024)
+        .spawn(|| {
+            let input = "[{\"a\":".repeat(100_000);
+            let error = JSONValue::load_and_verify(&input).unwrap_err();
+            assert_eq!(error.kind(), JSONParsingErrorKind::DepthLimitExceeded);
+            let value = ParseOptions::new()
+                .with_max_depth(ParseOptions::MAX_DEPTH)
+                .load(&input);
+            exercise(value, &input, 8, None);
+
+            let input = "[".repeat(100_000) + &"]".repeat(100_000);
+            exercise_input(&input);
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}